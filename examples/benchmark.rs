@@ -0,0 +1,98 @@
+//! Runs the simulation headlessly with thousands of asteroids and missiles
+//! and reports how long a tick takes against the budget of 60 ticks per
+//! second.
+//!
+//! ```text
+//! cargo run --release --example benchmark [asteroids] [missiles] [ticks]
+//! ```
+
+use ::rand::rngs::SmallRng;
+use ::rand::{Rng, SeedableRng};
+use macroquad::math::{vec2, Vec2};
+use std::time::Instant;
+
+use asteroid_game::asteroid::Asteroid;
+use asteroid_game::classes::AsteroidClasses;
+use asteroid_game::general::{Gamestate, TICKS};
+use asteroid_game::missile::{Missile, MissileConfig};
+use asteroid_game::world::World;
+
+fn main() {
+    let arguments: Vec<usize> = std::env::args()
+        .skip(1)
+        .map(|arg| arg.parse().expect("the arguments are numbers"))
+        .collect();
+    let asteroids = arguments.first().copied().unwrap_or(2000);
+    let missiles = arguments.get(1).copied().unwrap_or(2000);
+    let ticks = arguments.get(2).copied().unwrap_or(600);
+
+    let world = World::new(3840.0, 2160.0);
+    let mut gamestate = Gamestate::new(world, 0);
+    gamestate.new_game();
+    gamestate.asteroid_limit = usize::MAX;
+    // The spaceship stays out of the way
+    gamestate.spaceship.invulnerability = f32::MAX;
+    gamestate.ufo_timer = f32::MAX;
+    // Tough asteroids, so that the field lasts the whole run
+    gamestate.asteroid_classes = AsteroidClasses::parse(
+        "class 3 * 100 30 90 0.5 1.5 255 2\n\
+         class 2 * 200 60 150 0.5 2.5 255 2\n\
+         class 1 * 300 100 220 1.0 4.0 255 0\n",
+    )
+    .expect("the table is valid");
+
+    // A separate generator so that the field does not depend on the game
+    let mut rng = SmallRng::seed_from_u64(1);
+    let random_position = |rng: &mut SmallRng| {
+        vec2(
+            rng.gen_range(0.0..world.width),
+            rng.gen_range(0.0..world.height),
+        )
+    };
+    for _ in 0..asteroids {
+        let mut asteroid = Asteroid::new_random(&world, &mut rng, &gamestate.asteroid_classes);
+        asteroid.position = random_position(&mut rng);
+        gamestate.asteroids.push(asteroid);
+    }
+    let config = MissileConfig {
+        lifetime: f32::MAX,
+        ..Default::default()
+    };
+
+    let start = Instant::now();
+    let mut slowest = 0.0_f64;
+    for _ in 0..ticks {
+        // The destroyed missiles are replaced to keep the load steady
+        while gamestate.missiles.len() < missiles {
+            let rotation = rng.gen_range(0.0..std::f32::consts::TAU);
+            let position = random_position(&mut rng);
+            gamestate
+                .missiles
+                .push(Missile::new(position, Vec2::ZERO, rotation, &config));
+        }
+        let tick_start = Instant::now();
+        gamestate.tick();
+        slowest = slowest.max(tick_start.elapsed().as_secs_f64());
+    }
+    let average = start.elapsed().as_secs_f64() / ticks as f64;
+
+    println!(
+        "{ticks} ticks, {} asteroids and {missiles} missiles at the end",
+        gamestate.asteroids.len()
+    );
+    println!(
+        "Average tick: {:.3} ms, slowest: {:.3} ms, budget: {:.3} ms",
+        average * 1000.0,
+        slowest * 1000.0,
+        TICKS * 1000.0
+    );
+    println!(
+        "{:.0} ticks per second, {}",
+        1.0 / average,
+        if average <= TICKS {
+            "fast enough for 60 ticks per second"
+        } else {
+            "too slow for 60 ticks per second"
+        }
+    );
+}
@@ -1,13 +1,19 @@
 use ::rand::distributions::{Distribution, WeightedIndex};
-use ::rand::{thread_rng, Rng};
+use ::rand::rngs::SmallRng;
+use ::rand::Rng;
 use macroquad::prelude::*;
-use std::f32::consts::PI;
+use std::f32::consts::{PI, SQRT_2};
 
 use ::std::fs;
 use lazy_static::lazy_static;
 use std::path::PathBuf;
 
-/// Will get all the file names from the specified directory, not exclusive to images
+use crate::classes::{AsteroidClasses, VariantKind, MAX_SIZE};
+use crate::shape;
+use crate::world::{lerp_angle, World};
+
+/// Will get all the file names from the specified directory, not exclusive to images.
+/// The names are sorted so that a variant index is the same on every machine.
 pub fn get_textures(dir: &str) -> Vec<String> {
     let mut filenames: Vec<String> = Vec::new();
 
@@ -24,51 +30,114 @@ pub fn get_textures(dir: &str) -> Vec<String> {
             }
         }
     }
+    filenames.sort();
     filenames
 }
 
+/// The default asteroid is a still, common asteroid placed at the origin.
+/// Use [`Asteroid::new_random`] to spawn a random one near the edges of the playfield.
 impl Default for Asteroid {
     fn default() -> Self {
-        let mut rng = thread_rng();
-        let new_properties = Self::new_properties();
         Self {
-            position: Self::new_alea_pos(),
-            speed: new_properties.2,
+            position: Vec2::ZERO,
+            velocity: Vec2::ZERO,
             size: 3,
             scale: 40.0,
-            rotation: Self::new_rotation(),
-            direction: rng.gen_range(0.0..=2.0 * PI),
-            speed_multiplier: new_properties.1,
-            turn_rate: rng.gen_range(0.5..1.5) * if rng.gen() { 1.0 } else { -1.0 },
-            texture: TEXTURES[Self::create_weights()].to_string(),
+            rotation: 0.0,
+            turn_rate: 0.0,
+            texture: TEXTURES[0].to_string(),
+            hit_points: 1,
+            shape: Vec::new(),
+            active: true,
+            previous_position: Vec2::ZERO,
+            previous_rotation: 0.0,
         }
     }
 }
 
 pub struct Asteroid {
     pub position: Vec2,
-    pub speed: f32,
+    pub velocity: Vec2, // px/s
     pub size: u8,
     pub scale: f32,
     pub rotation: f32,
-    pub direction: f32,
-    pub speed_multiplier: f32,
     pub turn_rate: f32, // °/s
     pub texture: String,
+    // Missiles still needed to break the asteroid
+    pub hit_points: u8,
+    // Outline in units of the radius, round and textured when empty
+    pub shape: Vec<Vec2>,
+    // False once broken, until the broken asteroids are removed
+    pub active: bool,
+    // State at the previous tick, for the interpolation of the rendering
+    pub previous_position: Vec2,
+    pub previous_rotation: f32,
 }
 
 impl Asteroid {
     pub const ASTEROID_INIT_SIZE: f32 = 60.0;
 
+    /// Create a random asteroid of the largest size near one of the edges of
+    /// the playfield, with the properties of its class.
+    /// Every random value is drawn from the given generator.
+    pub fn new_random(world: &World, rng: &mut SmallRng, classes: &AsteroidClasses) -> Self {
+        let texture = Self::random_texture(rng, classes);
+        let class = classes.get(MAX_SIZE, &variant_of(&texture));
+        let position = Self::new_alea_pos(world, rng);
+        let rotation = Self::new_rotation(rng);
+        Self {
+            position,
+            velocity: {
+                let speed = rng.gen_range(class.min_speed..=class.max_speed);
+                Vec2::from_angle(rng.gen_range(0.0..=2.0 * PI)) * speed
+            },
+            size: MAX_SIZE,
+            scale: 40.0,
+            rotation,
+            turn_rate: rng.gen_range(class.min_turn_rate..=class.max_turn_rate)
+                * if rng.gen() { 1.0 } else { -1.0 },
+            texture,
+            hit_points: class.hit_points,
+            shape: Vec::new(),
+            active: true,
+            previous_position: position,
+            previous_rotation: rotation,
+        }
+    }
+
+    /// Create a random asteroid at least `min_distance` away from `avoid`,
+    /// so a new wave never appears on top of the player.
+    pub fn new_random_away(
+        world: &World,
+        rng: &mut SmallRng,
+        classes: &AsteroidClasses,
+        avoid: Vec2,
+        min_distance: f32,
+    ) -> Self {
+        let mut asteroid = Self::new_random(world, rng, classes);
+        for _ in 0..16 {
+            if world.distance(asteroid.position, avoid) >= min_distance {
+                asteroid.save_previous();
+                return asteroid;
+            }
+            asteroid.position = Self::new_alea_pos(world, rng);
+        }
+        // Pushed away from the player when no random place was far enough
+        let away = (asteroid.position - avoid)
+            .try_normalize()
+            .unwrap_or(Vec2::X);
+        asteroid.position = world.wrap(avoid + away * min_distance);
+        asteroid.save_previous();
+        asteroid
+    }
+
     /// The default builder which may accept set values.
     pub fn new(
         position: Option<Vec2>,
-        speed: Option<f32>,
+        velocity: Option<Vec2>,
         size: Option<u8>,
         scale: Option<f32>,
         rotation: Option<f32>,
-        direction: Option<f32>,
-        speed_multiplier: Option<f32>,
         turn_rate: Option<f32>,
         texture: Option<String>,
     ) -> Self {
@@ -78,8 +147,8 @@ impl Asteroid {
         if let Some(position) = position {
             asteroid.position = position;
         }
-        if let Some(speed) = speed {
-            asteroid.speed = speed;
+        if let Some(velocity) = velocity {
+            asteroid.velocity = velocity;
         }
         if let Some(size) = size {
             asteroid.size = size;
@@ -90,12 +159,6 @@ impl Asteroid {
         if let Some(rotation) = rotation {
             asteroid.rotation = rotation;
         }
-        if let Some(direction) = direction {
-            asteroid.direction = direction;
-        }
-        if let Some(speed_multiplier) = speed_multiplier {
-            asteroid.speed_multiplier = speed_multiplier;
-        }
         if let Some(turn_rate) = turn_rate {
             asteroid.turn_rate = turn_rate;
         }
@@ -103,140 +166,215 @@ impl Asteroid {
             asteroid.texture = texture;
         }
 
+        asteroid.save_previous();
         asteroid
     }
 
+    /// Keeps the current state as the state of the previous tick.
+    pub fn save_previous(&mut self) {
+        self.previous_position = self.position;
+        self.previous_rotation = self.rotation;
+    }
+
     /// Moves the object by adding rotation within the bounds of [-2PI;2PI]
     pub fn add_rotation(&mut self, amount: f32) {
         self.rotation = (self.rotation + amount) % (PI * 2.0);
     }
 
-    /// Moves the object based on its speed, applying inertia.
-    pub fn move_object(&mut self, delta_time: f64) {
-        let direction = vec2(self.direction.cos(), self.direction.sin());
-        self.position += direction * self.speed * self.speed_multiplier * delta_time as f32;
+    /// Moves the object based on its velocity, applying inertia.
+    pub fn move_object(&mut self, delta_time: f64, world: &World) {
+        self.position += self.velocity * delta_time as f32;
         // Move at the opposite edge
-        self.position = Self::bound_pos(self.position);
+        self.position = world.wrap(self.position);
     }
 
-    /// Generates a random position near one of the screen edges.
-    fn new_alea_pos() -> Vec2 {
-        let mut rng = thread_rng();
+    /// Generates a random position near one of the edges of the playfield.
+    fn new_alea_pos(world: &World, rng: &mut SmallRng) -> Vec2 {
         let nearpos: f32 = rng.gen_range(Self::ASTEROID_INIT_SIZE / 2.0..=Self::ASTEROID_INIT_SIZE);
         // 1 = top, 2 = right, 3 = bottom, 4 = left
         let nearside = rng.gen_range(1..=4);
         let xpos: f32 = match nearside {
-            2 => screen_width() - nearpos,
+            2 => world.width - nearpos,
             4 => nearpos,
-            _ => rng.gen_range(0.0..=screen_width()),
+            _ => rng.gen_range(0.0..=world.width),
         };
         let ypos: f32 = match nearside {
             1 => nearpos,
-            3 => screen_height() - nearpos,
-            _ => rng.gen_range(0.0..=screen_height()),
+            3 => world.height - nearpos,
+            _ => rng.gen_range(0.0..=world.height),
         };
         vec2(xpos, ypos)
     }
 
-    /// Create properties based on each other and assign them to a tuple for the constructor
-    fn new_properties() -> (u8, f32, f32) {
-        let mut rng = thread_rng();
-        let size = rng.gen_range(1..=3);
-        let speed_multiplier = rng.gen_range(0.4..=1.5);
-        let size_to_speed = match size {
-            1 => 3.0,
-            2 => 2.0,
-            _ => 1.0,
-        };
+    /// Generate a random rotation between \[0;2PI]
+    fn new_rotation(rng: &mut SmallRng) -> f32 {
+        rng.gen_range(0.0..=2.0 * PI)
+    }
 
-        (
-            size,
-            speed_multiplier,
-            size_to_speed * speed_multiplier * 50.0,
-        )
+    /// Picks the texture, and so the variant, of a new asteroid with the
+    /// rarity weights of the table. The first texture is used when no
+    /// variant can spawn.
+    fn random_texture(rng: &mut SmallRng, classes: &AsteroidClasses) -> String {
+        let weights: Vec<f32> = TEXTURES
+            .iter()
+            .map(|texture| classes.weight(&variant_of(texture)))
+            .collect();
+        let index = WeightedIndex::new(&weights).map_or(0, |weights| weights.sample(rng));
+        TEXTURES[index].to_string()
     }
 
-    /// Generate a random rotation between \[0;2PI]
-    fn new_rotation() -> f32 {
-        let mut rng = thread_rng();
-        rng.gen_range(0.0..=2.0 * PI)
+    /// The name of the variant, which selects its class in the table.
+    pub fn variant(&self) -> String {
+        variant_of(&self.texture)
     }
 
-    /// Generate the rariry weights of the different kinds of asteroids
-    /// The first asteroid variant appears 95% of the time while
-    /// the rest is distributed equally in the remaining 5%
-    fn create_weights() -> usize {
-        let mut rng = thread_rng();
-        // Define the weight for the first item (95%) and equal weights for the rest (5%)
-        let first_rariry = 85.0;
-        let equal_weight = (100.0 - first_rariry) / (TEXTURES.len() - 1) as f32;
-
-        // Create the weights vector
-        let mut weights = vec![first_rariry];
-        weights.extend(vec![equal_weight; TEXTURES.len() - 1]);
-        WeightedIndex::new(&weights).unwrap().sample(&mut rng)
+    /// Mass of the asteroid for the collisions between asteroids, growing
+    /// with its area.
+    pub fn mass(&self) -> f32 {
+        self.radius() * self.radius()
     }
 
-    fn bound_pos(mut pos: Vec2) -> Vec2 {
-        pos.x = Self::bound_to(pos.x, screen_width());
-        pos.y = Self::bound_to(pos.y, screen_height());
-        pos
+    /// Radius of the asteroid, which bounds its outline.
+    pub fn radius(&self) -> f32 {
+        self.size as f32 * self.scale / 2.0
     }
 
-    fn bound_to(coord: f32, max: f32) -> f32 {
-        if coord < 0.0 {
-            max
-        } else if coord > max {
-            0.0
+    /// The vertices of the outline in the playfield, empty for a round asteroid.
+    pub fn outline(&self) -> Vec<Vec2> {
+        shape::place(&self.shape, self.position, self.rotation, self.radius())
+    }
+
+    /// Returns whether a circle touches the asteroid, through the edges of
+    /// the playfield if needed.
+    pub fn hits_circle(&self, world: &World, center: Vec2, radius: f32) -> bool {
+        self.sweeps_circle(world, center, Vec2::ZERO, radius)
+    }
+
+    /// Returns whether a circle moving by `motion` from `start`, relative to
+    /// the asteroid, touches it on its way. Nothing fast goes through an
+    /// asteroid between two ticks.
+    pub fn sweeps_circle(&self, world: &World, start: Vec2, motion: Vec2, radius: f32) -> bool {
+        // The image of the circle closest to the asteroid
+        let start = self.position + world.delta(self.position, start);
+        let end = start + motion;
+        if shape::distance_to_segment(self.position, start, end) >= self.radius() + radius {
+            return false;
+        }
+        self.shape.is_empty() || shape::capsule_intersects(&self.outline(), start, end, radius)
+    }
+
+    /// Returns whether a polygon around `center` touches the asteroid,
+    /// through the edges of the playfield if needed.
+    pub fn hits_polygon(&self, world: &World, center: Vec2, polygon: &[Vec2]) -> bool {
+        let shift = self.position + world.delta(self.position, center) - center;
+        let polygon: Vec<Vec2> = polygon.iter().map(|point| *point + shift).collect();
+        if self.shape.is_empty() {
+            shape::circle_intersects(&polygon, self.position, self.radius())
         } else {
-            coord
+            shape::polygons_intersect(&self.outline(), &polygon)
         }
     }
 
-    /// Create two new asteroids from the attributes of the parent asteroid
-    pub fn split(&mut self, can_add: bool) -> Vec<Asteroid> {
+    /// Returns whether two asteroids touch.
+    pub fn touches(&self, world: &World, other: &Asteroid) -> bool {
+        if other.shape.is_empty() {
+            self.hits_circle(world, other.position, other.radius())
+        } else {
+            world.distance(self.position, other.position) < self.radius() + other.radius()
+                && self.hits_polygon(world, other.position, &other.outline())
+        }
+    }
+
+    /// Whether the asteroid damages its neighbours when it breaks.
+    pub fn is_explosive(&self, classes: &AsteroidClasses) -> bool {
+        classes.kind(&self.variant()) == VariantKind::Explosive
+    }
+
+    /// Reach of the blast of an explosive asteroid, from its center.
+    pub fn blast_radius(&self) -> f32 {
+        self.size as f32 * self.scale
+    }
+
+    /// Create the fragments of the asteroid, as many as its class gives and
+    /// only one if `can_add` is false, going away from its direction at the
+    /// speed of their class.
+    pub fn split(
+        &mut self,
+        can_add: bool,
+        rng: &mut SmallRng,
+        classes: &AsteroidClasses,
+    ) -> Vec<Asteroid> {
         let mut output = Vec::new();
-        let mut rng = thread_rng();
-        if self.size - 1 != 0 {
-            output.push(Asteroid::new(
-                Some(self.position),
-                Some(self.speed * -rng.gen_range(1.0..=1.75)),
-                Some(if self.size >= 1 { self.size - 1 } else { 0 }),
-                None,
-                Some(-self.rotation + PI / 4.0), // Instantly invert the rotation
-                Some(-self.direction - PI / 4.0),
-                Some(self.speed_multiplier),
-                Some(-self.turn_rate * rng.gen_range(1.0..=2.0)), // Randomly change the turn rate while also inverting it
-                Some(self.texture.clone()),
-            ))
+        if self.size <= 1 {
+            return output;
+        }
+        let variant = self.variant();
+        let splits = classes.get(self.size, &variant).splits;
+        let class = classes.get(self.size - 1, &variant);
+        let count = if can_add { splits } else { splits.min(1) };
+        // An outline is cut in as many wedges as there are fragments
+        let cut = if !self.shape.is_empty() && count > 1 {
+            rng.gen_range(0.0..2.0 * PI)
+        } else {
+            0.0
         };
-        if can_add && self.size - 1 != 0 {
-            output.push(Asteroid::new(
+        // A still asteroid breaks along its orientation
+        let heading = self
+            .velocity
+            .try_normalize()
+            .unwrap_or_else(|| Vec2::from_angle(self.rotation));
+        for index in 0..count {
+            // Fragments spread over a quarter turn
+            let spread = if splits > 1 {
+                -PI / 4.0 + PI / 2.0 * index as f32 / (splits - 1) as f32
+            } else {
+                0.0
+            };
+            // Every other fragment turns the other way
+            let turn_direction = if index % 2 == 0 { -1.0 } else { 1.0 };
+            let speed = rng.gen_range(class.min_speed..=class.max_speed);
+            let mut fragment = Asteroid::new(
                 Some(self.position),
-                Some(self.speed * -rng.gen_range(1.0..=2.0)),
-                Some(if self.size > 1 { self.size - 1 } else { 0 }),
+                Some(Vec2::from_angle(spread).rotate(-heading) * speed),
+                Some(self.size - 1),
                 None,
-                Some(-self.rotation - PI / 4.0),
-                Some(-self.direction + PI / 4.0),
-                Some(self.speed_multiplier),
-                Some(self.turn_rate * rng.gen_range(1.0..=3.5)),
+                Some(-self.rotation - spread),
+                Some(
+                    rng.gen_range(class.min_turn_rate..=class.max_turn_rate)
+                        * self.turn_rate.signum()
+                        * turn_direction,
+                ),
                 Some(self.texture.clone()),
-            ));
+            );
+            fragment.hit_points = class.hit_points;
+            if !self.shape.is_empty() {
+                let (center, piece) = if count > 1 {
+                    let wedge = 2.0 * PI / count as f32;
+                    let start = cut + wedge * index as f32;
+                    shape::fragment(&self.shape, start, start + wedge)
+                } else {
+                    (Vec2::ZERO, self.shape.clone())
+                };
+                // The piece stays where it was in the asteroid
+                fragment.shape = piece;
+                fragment.rotation = self.rotation;
+                fragment.position =
+                    self.position + Vec2::from_angle(self.rotation).rotate(center * self.radius());
+                fragment.save_previous();
+            }
+            output.push(fragment);
         }
         output
     }
 
     /// For the debug mode. Will draw lines to indicate the direction and the orientation
-    pub fn draw_trajectory(&self) {
+    pub fn draw_trajectory(&self, start: Vec2) {
         // Define the arrow length and compute the direction where the asteroid is moving
         let arrow_length = 40.0;
         // Normalize to get direction
 
-        // Get the asteroid's current position and rotation
-        let start = self.position;
-
         // Calculate the direction of the arrow based on the asteroid's rotation
-        let direction = vec2(self.direction.cos(), self.direction.sin()) * self.direction.signum();
+        let direction = self.velocity.normalize_or_zero();
         let rotation = vec2(self.rotation.cos(), self.rotation.sin());
 
         // Calculate the end point of the arrows
@@ -256,40 +394,58 @@ impl Asteroid {
         );
     }
 
-    /// Display of the asteroid.
-    pub fn draw_self(&self, texture: &Texture2D, debug: bool) {
+    /// Display of the asteroid, interpolated between the last two ticks
+    /// by `alpha`.
+    pub fn draw_self(
+        &self,
+        texture: &Texture2D,
+        debug: bool,
+        alpha: f32,
+        world: &World,
+        classes: &AsteroidClasses,
+    ) {
         // Ensure the size_multiplier is cast to f32 to use with scale
         let adjusted_scale = self.scale * self.size as f32;
         let font_size = 20.0;
-        let position = self.position;
-
-        draw_texture_ex(
-            texture,
-            // Center the texture to the asteroid's center
-            position.x - adjusted_scale / 2.0,
-            position.y - adjusted_scale / 2.0,
-            WHITE,
-            DrawTextureParams {
-                dest_size: Some(Vec2::new(adjusted_scale, adjusted_scale)),
-                rotation: self.rotation,
-                ..Default::default()
-            },
-        );
+        let position = world.lerp(self.previous_position, self.position, alpha);
+        let rotation = lerp_angle(self.previous_rotation, self.rotation, alpha);
+
+        // Drawn again across the edges it overlaps, the corners of the
+        // texture included
+        for image in world.images(position, self.radius() * SQRT_2) {
+            if self.shape.is_empty() {
+                draw_texture_ex(
+                    texture,
+                    // Center the texture to the asteroid's center
+                    image.x - adjusted_scale / 2.0,
+                    image.y - adjusted_scale / 2.0,
+                    WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(Vec2::new(adjusted_scale, adjusted_scale)),
+                        rotation,
+                        ..Default::default()
+                    },
+                );
+            } else {
+                // Vector lines, like the arcade game
+                let outline = shape::place(&self.shape, image, rotation, self.radius());
+                let color = line_color(classes.kind(&self.variant()));
+                for (index, start) in outline.iter().enumerate() {
+                    let end = outline[(index + 1) % outline.len()];
+                    draw_line(start.x, start.y, end.x, end.y, 2.0, color);
+                }
+            }
+        }
 
         if debug {
             // Attributes
-            let size_to_speed = match self.size {
-                1 => 3.5,
-                2 => 2.0,
-                _ => 1.0,
-            };
+            let class = classes.get(self.size, &self.variant());
             let mut texts = Vec::from([
                 format!("x:{:.2} y:{:.2}", position.x, position.y),
                 format!("Size:{}", self.size),
-                format!(
-                    "Speed:{:.2}px/s",
-                    self.speed * self.speed_multiplier * size_to_speed
-                ),
+                format!("Speed:{:.2}px/s", self.velocity.length()),
+                format!("Hit points:{}/{}", self.hit_points, class.hit_points),
+                format!("Score:{}", class.score),
                 format!(
                     "Rotation:{}|{:.3}rad",
                     if self.turn_rate.signum() == 1.0 {
@@ -300,14 +456,12 @@ impl Asteroid {
                     self.rotation
                 ),
                 format!("Turn Rate:{:.3}rad/s", self.turn_rate),
-                format!("Direction: {:.3}rad", self.direction),
-                format!("Speed modifier:{:.2}%", (self.speed_multiplier * 80.0)),
+                format!("Direction: {:.3}rad", self.velocity.to_angle()),
+                format!("Mass:{:.0}", self.mass()),
                 format!(
-                    "Variant:{}",
-                    PathBuf::from(self.texture.clone())
-                        .file_stem()
-                        .unwrap()
-                        .to_string_lossy()
+                    "Variant:{} ({})",
+                    self.variant(),
+                    classes.kind(&self.variant()).name()
                 ),
             ]);
 
@@ -339,12 +493,12 @@ impl Asteroid {
                 -font_size * texts.len() as f32
             };
 
-            // Hitbox
+            // Hitbox, the outline itself when there is one
             draw_circle_lines(
                 position.x,
                 position.y,
-                20.0 * self.size as f32 - 3.0,
-                3.0,
+                self.radius(),
+                if self.shape.is_empty() { 3.0 } else { 1.0 },
                 Color::from_hex(0x0000FF),
             );
             // Center
@@ -360,13 +514,13 @@ impl Asteroid {
                 );
             }
 
-            self.draw_trajectory();
+            self.draw_trajectory(position);
             // Comparison line
             draw_line(
-                self.position.x,
-                self.position.y,
-                self.position.x,
-                self.position.y - 75.0,
+                position.x,
+                position.y,
+                position.x,
+                position.y - 75.0,
                 1.0,
                 WHITE,
             );
@@ -374,6 +528,24 @@ impl Asteroid {
     }
 }
 
+/// The color of the outline of each kind of variant.
+pub fn line_color(kind: VariantKind) -> Color {
+    match kind {
+        VariantKind::Common => WHITE,
+        VariantKind::Metallic => LIGHTGRAY,
+        VariantKind::Explosive => RED,
+        VariantKind::Icy => SKYBLUE,
+        VariantKind::Gold => GOLD,
+    }
+}
+
+/// The name of the texture file without its extension.
+fn variant_of(texture: &str) -> String {
+    PathBuf::from(texture)
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().to_string())
+}
+
 // Initialize the textures lazily at runtime using lazy_static
 // Comparable to a constant but more flexible
 lazy_static! {
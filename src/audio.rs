@@ -0,0 +1,396 @@
+use macroquad::audio::{
+    load_sound, play_sound, set_sound_volume, stop_sound, PlaySoundParams, Sound,
+};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::general::Gamestate;
+use crate::ufo::UfoKind;
+
+/// Where the sounds are loaded from.
+pub const DEFAULT_DIRECTORY: &str = "./assets/sounds";
+
+// Seconds between two beats with every asteroid left, and with the last one
+const SLOWEST_BEAT: f32 = 1.0;
+const FASTEST_BEAT: f32 = 0.25;
+
+/// Something to be heard, reported by the simulation during an update.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SoundEvent {
+    Fire,
+    /// An asteroid, a saucer or the spaceship was destroyed, by its size
+    /// from 1 to 3 as the asteroids.
+    Explosion(u8),
+    ExtraLife,
+    GameOver,
+}
+
+/// The sounds of the game, one file each.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Effect {
+    Thrust,
+    Fire,
+    LargeExplosion,
+    MediumExplosion,
+    SmallExplosion,
+    LargeUfo,
+    SmallUfo,
+    ExtraLife,
+    GameOver,
+    LowBeat,
+    HighBeat,
+}
+
+impl Effect {
+    pub const ALL: [Effect; 11] = [
+        Effect::Thrust,
+        Effect::Fire,
+        Effect::LargeExplosion,
+        Effect::MediumExplosion,
+        Effect::SmallExplosion,
+        Effect::LargeUfo,
+        Effect::SmallUfo,
+        Effect::ExtraLife,
+        Effect::GameOver,
+        Effect::LowBeat,
+        Effect::HighBeat,
+    ];
+
+    /// The name of the file in the directory of the sounds.
+    pub fn file(&self) -> &'static str {
+        match self {
+            Effect::Thrust => "thrust.wav",
+            Effect::Fire => "fire.wav",
+            Effect::LargeExplosion => "explosion_large.wav",
+            Effect::MediumExplosion => "explosion_medium.wav",
+            Effect::SmallExplosion => "explosion_small.wav",
+            Effect::LargeUfo => "ufo_large.wav",
+            Effect::SmallUfo => "ufo_small.wav",
+            Effect::ExtraLife => "extra_life.wav",
+            Effect::GameOver => "game_over.wav",
+            Effect::LowBeat => "beat_low.wav",
+            Effect::HighBeat => "beat_high.wav",
+        }
+    }
+
+    /// Whether the sound is part of the music rather than an effect.
+    pub fn is_music(&self) -> bool {
+        matches!(self, Effect::LowBeat | Effect::HighBeat)
+    }
+}
+
+impl SoundEvent {
+    /// The sound played for the event, the explosions getting lower with
+    /// the size of what was destroyed.
+    pub fn effect(&self) -> Effect {
+        match self {
+            SoundEvent::Fire => Effect::Fire,
+            SoundEvent::Explosion(size) if *size >= 3 => Effect::LargeExplosion,
+            SoundEvent::Explosion(2) => Effect::MediumExplosion,
+            SoundEvent::Explosion(_) => Effect::SmallExplosion,
+            SoundEvent::ExtraLife => Effect::ExtraLife,
+            SoundEvent::GameOver => Effect::GameOver,
+        }
+    }
+}
+
+/// The volumes chosen by the player, from 0 to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioSettings {
+    pub effects_volume: f32,
+    pub music_volume: f32,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            effects_volume: 0.8,
+            music_volume: 0.6,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    /// The volume a sound is played at, 0 when muted.
+    pub fn volume(&self, effect: Effect) -> f32 {
+        if self.muted {
+            0.0
+        } else if effect.is_music() {
+            self.music_volume
+        } else {
+            self.effects_volume
+        }
+    }
+
+    /// Raises or lowers both volumes, within 0 and 1.
+    pub fn change_volume(&mut self, step: f32) {
+        self.effects_volume = (self.effects_volume + step).clamp(0.0, 1.0);
+        self.music_volume = (self.music_volume + step).clamp(0.0, 1.0);
+    }
+}
+
+/// The two alternating beats of the arcade game, which speed up as the
+/// asteroids are destroyed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Heartbeat {
+    // Seconds before the next beat, and whether it is the high one
+    timer: f32,
+    high: bool,
+}
+
+impl Heartbeat {
+    /// Seconds between two beats with `remaining` asteroids, the beat being
+    /// the slowest with `full` of them or more.
+    pub fn interval(remaining: usize, full: usize) -> f32 {
+        let share = if full == 0 {
+            1.0
+        } else {
+            (remaining as f32 / full as f32).min(1.0)
+        };
+        FASTEST_BEAT + (SLOWEST_BEAT - FASTEST_BEAT) * share
+    }
+
+    /// Advances the heartbeat by `delta_time` seconds of the simulation.
+    /// Returns the beat to play, if one is due.
+    pub fn update(&mut self, delta_time: f32, remaining: usize, full: usize) -> Option<Effect> {
+        self.timer -= delta_time;
+        if self.timer > 0.0 {
+            return None;
+        }
+        self.timer = Self::interval(remaining, full);
+        self.high = !self.high;
+        Some(if self.high {
+            Effect::HighBeat
+        } else {
+            Effect::LowBeat
+        })
+    }
+
+    /// Starts again from the first beat.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Plays the sounds of the game.
+///
+/// Without a sound card or sounds to load, the audio is silent but keeps
+/// following the game, so the game runs the same.
+pub struct Audio {
+    pub settings: AudioSettings,
+    // The loaded sounds, `None` for the silent backend
+    sounds: Option<HashMap<Effect, Sound>>,
+    heartbeat: Heartbeat,
+    // The sounds playing in a loop, and the settings they are playing with
+    looping: Vec<Effect>,
+    applied: AudioSettings,
+}
+
+impl Audio {
+    /// An audio which plays nothing.
+    pub fn silent() -> Self {
+        Self {
+            settings: AudioSettings::default(),
+            sounds: None,
+            heartbeat: Heartbeat::default(),
+            looping: Vec::new(),
+            applied: AudioSettings::default(),
+        }
+    }
+
+    /// Loads every sound from a directory. Without a way to hear them, the
+    /// audio is silent rather than failing.
+    pub async fn load(directory: &str) -> Result<Self, macroquad::Error> {
+        if !output_available() {
+            return Ok(Self::silent());
+        }
+        let mut sounds = HashMap::new();
+        for effect in Effect::ALL {
+            let sound = load_sound(&format!("{directory}/{}", effect.file())).await?;
+            sounds.insert(effect, sound);
+        }
+        Ok(Self {
+            sounds: Some(sounds),
+            ..Self::silent()
+        })
+    }
+
+    /// Returns whether the sounds are played, `false` for the silent backend.
+    pub fn is_enabled(&self) -> bool {
+        self.sounds.is_some()
+    }
+
+    /// Plays the events of the last update, the loops of the thrust and of
+    /// the saucers, and the heartbeat during a wave.
+    pub fn update(&mut self, gamestate: &Gamestate) {
+        for event in &gamestate.sound_events {
+            self.play(event.effect());
+        }
+
+        let playing = gamestate.game_started && gamestate.simulation_speed > 0.0;
+        let flying = playing && !gamestate.is_respawning();
+        self.set_looping(Effect::Thrust, flying && gamestate.input.thrust);
+        let saucer = |kind| playing && gamestate.ufos.iter().any(|ufo| ufo.kind == kind);
+        self.set_looping(Effect::LargeUfo, saucer(UfoKind::Large));
+        self.set_looping(Effect::SmallUfo, saucer(UfoKind::Small));
+
+        if playing && gamestate.intermission <= 0.0 && !gamestate.asteroids.is_empty() {
+            if let Some(beat) = self.heartbeat.update(
+                gamestate.delta_time as f32,
+                gamestate.asteroids.len(),
+                gamestate.wave_size,
+            ) {
+                self.play(beat);
+            }
+        } else {
+            self.heartbeat.reset();
+        }
+
+        // A new volume also applies to the loops already playing
+        if self.settings != self.applied {
+            self.applied = self.settings;
+            if let Some(sounds) = &self.sounds {
+                for effect in &self.looping {
+                    set_sound_volume(&sounds[effect], self.settings.volume(*effect));
+                }
+            }
+        }
+    }
+
+    fn play(&self, effect: Effect) {
+        self.start(effect, false);
+    }
+
+    fn start(&self, effect: Effect, looped: bool) {
+        let volume = self.settings.volume(effect);
+        if let Some(sound) = self.sounds.as_ref().map(|sounds| &sounds[&effect]) {
+            if volume > 0.0 || looped {
+                play_sound(sound, PlaySoundParams { looped, volume });
+            }
+        }
+    }
+
+    // Starts or stops a looping sound when it changes
+    fn set_looping(&mut self, effect: Effect, on: bool) {
+        let playing = self.looping.contains(&effect);
+        if on && !playing {
+            self.start(effect, true);
+            self.looping.push(effect);
+        } else if !on && playing {
+            if let Some(sounds) = &self.sounds {
+                stop_sound(&sounds[&effect]);
+            }
+            self.looping.retain(|looping| *looping != effect);
+        }
+    }
+}
+
+/// Whether the sounds can be heard: the game must be built with the `audio`
+/// feature and, on Linux, ALSA must have a playback device, without which
+/// the audio thread of macroquad stops on its first sound.
+fn output_available() -> bool {
+    if !cfg!(feature = "audio") {
+        return false;
+    }
+    if cfg!(target_os = "linux") {
+        return fs::read_to_string("/proc/asound/pcm").is_ok_and(|pcm| pcm.contains("playback"));
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::World;
+    use std::path::Path;
+
+    #[test]
+    fn test_sound_files_exist() {
+        for effect in Effect::ALL {
+            let path = Path::new(DEFAULT_DIRECTORY).join(effect.file());
+            assert!(path.exists(), "{} is missing", path.display());
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "audio"))]
+    fn test_silent_without_feature() {
+        assert!(!output_available());
+    }
+
+    #[test]
+    fn test_explosions_by_size() {
+        assert_eq!(SoundEvent::Explosion(3).effect(), Effect::LargeExplosion);
+        assert_eq!(SoundEvent::Explosion(2).effect(), Effect::MediumExplosion);
+        assert_eq!(SoundEvent::Explosion(1).effect(), Effect::SmallExplosion);
+    }
+
+    #[test]
+    fn test_volume_settings() {
+        let mut settings = AudioSettings::default();
+        settings.change_volume(1.0);
+        assert_eq!(settings.volume(Effect::Fire), 1.0);
+        settings.change_volume(-0.5);
+        assert_eq!(settings.volume(Effect::HighBeat), 0.5);
+        settings.muted = true;
+        assert_eq!(settings.volume(Effect::Fire), 0.0);
+    }
+
+    #[test]
+    fn test_heartbeat_speeds_up() {
+        assert_eq!(Heartbeat::interval(26, 26), SLOWEST_BEAT);
+        assert_eq!(Heartbeat::interval(40, 26), SLOWEST_BEAT);
+        assert!(Heartbeat::interval(1, 26) < Heartbeat::interval(10, 26));
+
+        // Les deux battements alternent, à l'intervalle du moment
+        let mut heartbeat = Heartbeat::default();
+        assert_eq!(heartbeat.update(0.0, 26, 26), Some(Effect::HighBeat));
+        assert_eq!(heartbeat.update(0.5, 26, 26), None);
+        assert_eq!(heartbeat.update(0.5, 1, 26), Some(Effect::LowBeat));
+        assert_eq!(heartbeat.update(0.2, 1, 26), None);
+        assert_eq!(heartbeat.update(0.1, 1, 26), Some(Effect::HighBeat));
+    }
+
+    #[test]
+    fn test_heartbeat_starts_slow_with_each_wave() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.new_game();
+        // The first wave is smaller than the limit of asteroids
+        assert!(gamestate.wave_size < gamestate.asteroid_limit);
+        assert_eq!(
+            Heartbeat::interval(gamestate.asteroids.len(), gamestate.wave_size),
+            SLOWEST_BEAT
+        );
+
+        gamestate.asteroids.clear();
+        gamestate.level = 5;
+        gamestate.start_wave();
+        assert_eq!(
+            Heartbeat::interval(gamestate.asteroids.len(), gamestate.wave_size),
+            SLOWEST_BEAT
+        );
+        gamestate.asteroids.truncate(gamestate.wave_size / 2);
+        assert!(Heartbeat::interval(gamestate.asteroids.len(), gamestate.wave_size) < SLOWEST_BEAT);
+    }
+
+    #[test]
+    fn test_silent_audio_follows_the_game() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.new_game();
+        gamestate.input.thrust = true;
+        gamestate.input.fire = true;
+        gamestate.update(0.1);
+        assert!(gamestate.sound_events.contains(&SoundEvent::Fire));
+
+        let mut audio = Audio::silent();
+        assert!(!audio.is_enabled());
+        audio.update(&gamestate);
+        assert_eq!(audio.looping, vec![Effect::Thrust]);
+
+        gamestate.game_started = false;
+        audio.update(&gamestate);
+        assert!(audio.looping.is_empty());
+    }
+}
@@ -0,0 +1,398 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::{FromStr, SplitWhitespace};
+
+/// The file read at startup, the built-in table is used without it.
+pub const DEFAULT_PATH: &str = "./assets/asteroids.cfg";
+/// The largest size of asteroid, the ones spawned by the waves.
+pub const MAX_SIZE: u8 = 3;
+
+/// Everything that can go wrong with the table of asteroid classes.
+#[derive(Debug)]
+pub enum ClassesError {
+    Io(io::Error),
+    /// A line of the file cannot be read.
+    Invalid {
+        line: usize,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ClassesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClassesError::Io(error) => write!(f, "Cannot read the asteroid classes: {error}"),
+            ClassesError::Invalid { line, reason } => {
+                write!(f, "Invalid asteroid classes at line {line}: {reason}")
+            }
+        }
+    }
+}
+
+impl From<io::Error> for ClassesError {
+    fn from(error: io::Error) -> Self {
+        ClassesError::Io(error)
+    }
+}
+
+/// The properties shared by the asteroids of one size and variant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsteroidClass {
+    /// Points for destroying the asteroid.
+    pub score: u128,
+    /// Range of the speed, in px/s.
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Range of the turn rate, in rad/s, turning either way.
+    pub min_turn_rate: f32,
+    pub max_turn_rate: f32,
+    /// Missiles needed to break the asteroid.
+    pub hit_points: u8,
+    /// Fragments of the size below left when it breaks.
+    pub splits: u8,
+}
+
+/// What sets a variant apart from the common asteroids. The numbers of a
+/// variant (hit points, fragments, score...) are given by its classes, the
+/// kind adds the behaviours that cannot be described by them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VariantKind {
+    Common,
+    /// Takes several missiles to break.
+    Metallic,
+    /// Damages the asteroids around it when it breaks.
+    Explosive,
+    /// Breaks into more fragments.
+    Icy,
+    /// Rare and worth bonus points.
+    Gold,
+}
+
+impl VariantKind {
+    /// The name used in the table.
+    pub fn name(&self) -> &'static str {
+        match self {
+            VariantKind::Common => "common",
+            VariantKind::Metallic => "metallic",
+            VariantKind::Explosive => "explosive",
+            VariantKind::Icy => "icy",
+            VariantKind::Gold => "gold",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            VariantKind::Common,
+            VariantKind::Metallic,
+            VariantKind::Explosive,
+            VariantKind::Icy,
+            VariantKind::Gold,
+        ]
+        .into_iter()
+        .find(|kind| kind.name() == name)
+    }
+}
+
+/// A variant of asteroid, named after its texture.
+#[derive(Clone, Debug, PartialEq)]
+struct Variant {
+    name: String,
+    kind: VariantKind,
+    /// Relative chance of spawning, against the weights of the other variants.
+    weight: f32,
+}
+
+/// One class of the table, for a size and a variant or every variant.
+#[derive(Clone, Debug, PartialEq)]
+struct Entry {
+    size: u8,
+    variant: Option<String>,
+    class: AsteroidClass,
+}
+
+/// The classes of asteroids by size and by variant (the name of the texture
+/// file without its extension). A class given for the variant `*` applies
+/// to the variants without their own.
+///
+/// The table also gives the kind and the rarity of the variants. A texture
+/// missing from it is a common asteroid of weight 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsteroidClasses {
+    variants: Vec<Variant>,
+    entries: Vec<Entry>,
+}
+
+// The table used when the file is missing, as shipped in the assets
+const BUILT_IN: &str = "\
+variant asteroid_0 common 85
+variant asteroid_1 explosive 5
+variant asteroid_2 metallic 6
+variant asteroid_3 icy 3
+variant asteroid_4 gold 1
+class 3 * 100 30 90 0.5 1.5 1 2
+class 2 * 200 60 150 0.5 2.5 1 2
+class 1 * 300 100 220 1.0 4.0 1 0
+class 3 asteroid_2 150 20 60 0.3 1.0 3 2
+class 2 asteroid_2 250 40 100 0.3 1.5 2 2
+class 1 asteroid_2 400 70 150 0.5 2.5 2 0
+class 3 asteroid_3 100 30 90 0.5 1.5 1 3
+class 2 asteroid_3 200 60 150 0.5 2.5 1 3
+class 3 asteroid_4 1000 40 100 0.5 1.5 1 2
+class 2 asteroid_4 1500 70 160 0.5 2.5 1 2
+class 1 asteroid_4 2500 110 240 1.0 4.0 1 0
+";
+
+impl Default for AsteroidClasses {
+    fn default() -> Self {
+        Self::parse(BUILT_IN).expect("the built-in asteroid classes are valid")
+    }
+}
+
+impl AsteroidClasses {
+    /// The class of an asteroid. Sizes out of the table use the closest one.
+    pub fn get(&self, size: u8, variant: &str) -> &AsteroidClass {
+        let size = size.clamp(1, MAX_SIZE);
+        self.entries
+            .iter()
+            .find(|entry| entry.size == size && entry.variant.as_deref() == Some(variant))
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .find(|entry| entry.size == size && entry.variant.is_none())
+            })
+            .map(|entry| &entry.class)
+            .expect("every size has a class for all variants")
+    }
+
+    /// The kind of a variant.
+    pub fn kind(&self, variant: &str) -> VariantKind {
+        self.variant(variant)
+            .map_or(VariantKind::Common, |variant| variant.kind)
+    }
+
+    /// The rarity of a variant, as a weight relative to the other ones.
+    pub fn weight(&self, variant: &str) -> f32 {
+        self.variant(variant).map_or(1.0, |variant| variant.weight)
+    }
+
+    fn variant(&self, name: &str) -> Option<&Variant> {
+        self.variants.iter().find(|variant| variant.name == name)
+    }
+
+    /// Reads a table, one variant or class per line:
+    /// `variant <variant> <kind> <weight>` or
+    /// `class <size> <variant|*> <score> <min speed> <max speed>
+    /// <min turn rate> <max turn rate> <hit points> <splits>`.
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn parse(text: &str) -> Result<Self, ClassesError> {
+        let mut variants: Vec<Variant> = Vec::new();
+        let mut entries: Vec<Entry> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("class") => {}
+                Some("variant") => {
+                    let name = fields
+                        .next()
+                        .ok_or_else(|| invalid(number, "missing variant"))?;
+                    let kind = fields
+                        .next()
+                        .ok_or_else(|| invalid(number, "missing kind"))?;
+                    let kind = VariantKind::from_name(kind)
+                        .ok_or_else(|| invalid(number, &format!("unknown kind {kind}")))?;
+                    let weight: f32 = field(&mut fields, number)?;
+                    if !(weight >= 0.0 && weight.is_finite()) {
+                        return Err(invalid(number, "the weight cannot be negative"));
+                    }
+                    if variants.iter().any(|variant| variant.name == name) {
+                        return Err(invalid(number, "variant given twice"));
+                    }
+                    variants.push(Variant {
+                        name: name.to_string(),
+                        kind,
+                        weight,
+                    });
+                    continue;
+                }
+                _ => return Err(invalid(number, "expected a variant or a class")),
+            }
+            let size: u8 = field(&mut fields, number)?;
+            if !(1..=MAX_SIZE).contains(&size) {
+                return Err(invalid(number, &format!("no asteroid of size {size}")));
+            }
+            let variant = match fields.next() {
+                Some("*") => None,
+                Some(variant) => Some(variant.to_string()),
+                None => return Err(invalid(number, "missing variant")),
+            };
+            let class = AsteroidClass {
+                score: field(&mut fields, number)?,
+                min_speed: field(&mut fields, number)?,
+                max_speed: field(&mut fields, number)?,
+                min_turn_rate: field(&mut fields, number)?,
+                max_turn_rate: field(&mut fields, number)?,
+                hit_points: field(&mut fields, number)?,
+                splits: field(&mut fields, number)?,
+            };
+            if class.min_speed > class.max_speed || class.min_turn_rate > class.max_turn_rate {
+                return Err(invalid(number, "range from a larger to a smaller value"));
+            }
+            if class.hit_points == 0 {
+                return Err(invalid(number, "an asteroid needs at least one hit point"));
+            }
+            if entries
+                .iter()
+                .any(|entry| entry.size == size && entry.variant == variant)
+            {
+                return Err(invalid(number, "class given twice"));
+            }
+            entries.push(Entry {
+                size,
+                variant,
+                class,
+            });
+        }
+
+        // Every variant must find a class whatever its size
+        for size in 1..=MAX_SIZE {
+            if !entries
+                .iter()
+                .any(|entry| entry.size == size && entry.variant.is_none())
+            {
+                return Err(invalid(
+                    text.lines().count(),
+                    &format!("missing the class of size {size} for every variant"),
+                ));
+            }
+        }
+        Ok(Self { variants, entries })
+    }
+
+    /// Reads the table from a file.
+    pub fn load(path: &Path) -> Result<Self, ClassesError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// A hash of the table, which changes with any of its values but not
+    /// with the comments and the spacing of the file. A replay keeps it to
+    /// be played with the classes it was recorded with.
+    pub fn fingerprint(&self) -> u64 {
+        // FNV-1a, the same on every platform and version of Rust
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash = (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        for variant in &self.variants {
+            write(variant.name.as_bytes());
+            write(variant.kind.name().as_bytes());
+            write(&variant.weight.to_le_bytes());
+        }
+        for entry in &self.entries {
+            write(&[entry.size]);
+            write(entry.variant.as_deref().unwrap_or("*").as_bytes());
+            let class = &entry.class;
+            write(&class.score.to_le_bytes());
+            for value in [
+                class.min_speed,
+                class.max_speed,
+                class.min_turn_rate,
+                class.max_turn_rate,
+            ] {
+                write(&value.to_le_bytes());
+            }
+            write(&[class.hit_points, class.splits]);
+        }
+        hash
+    }
+}
+
+fn field<T: FromStr>(fields: &mut SplitWhitespace, line: usize) -> Result<T, ClassesError> {
+    let value = fields
+        .next()
+        .ok_or_else(|| invalid(line, "missing value"))?;
+    value
+        .parse()
+        .map_err(|_| invalid(line, &format!("invalid value {value}")))
+}
+
+fn invalid(line: usize, reason: &str) -> ClassesError {
+    ClassesError::Invalid {
+        line,
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shipped_table_matches_built_in() {
+        let shipped = AsteroidClasses::load(Path::new(DEFAULT_PATH)).unwrap();
+        assert_eq!(shipped, AsteroidClasses::default());
+        // Chaque variante correspond à une texture
+        for variant in &shipped.variants {
+            let texture = format!("./assets/textures/asteroid/{}.png", variant.name);
+            assert!(Path::new(&texture).is_file(), "{texture}");
+        }
+    }
+
+    #[test]
+    fn test_variant_overrides_common_class() {
+        let classes = AsteroidClasses::parse(&format!(
+            "{BUILT_IN}# Plus lent et plus solide\nclass 3 asteroid_1 500 10 20 0 0 3 4\n"
+        ))
+        .unwrap();
+        assert_eq!(classes.get(3, "asteroid_1").hit_points, 3);
+        assert_eq!(classes.get(3, "asteroid_0").hit_points, 1);
+        assert_eq!(classes.get(2, "asteroid_1").score, 200);
+        // Les tailles hors de la table prennent la plus proche
+        assert_eq!(classes.get(0, "asteroid_0").score, 300);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let classes = AsteroidClasses::default();
+        let commented = AsteroidClasses::parse(&format!("# Commentaire\n\n{BUILT_IN}")).unwrap();
+        assert_eq!(classes.fingerprint(), commented.fingerprint());
+        let changed = AsteroidClasses::parse(&BUILT_IN.replace("gold 1", "gold 2")).unwrap();
+        assert_ne!(classes.fingerprint(), changed.fingerprint());
+    }
+
+    #[test]
+    fn test_invalid_tables() {
+        let error = |text: &str| AsteroidClasses::parse(text).unwrap_err().to_string();
+        assert!(error("class 3 * 100 30 90 0.5 1.5 1 2").contains("size 1"));
+        let next_line = format!("line {}", BUILT_IN.lines().count() + 1);
+        assert!(error(&format!("{BUILT_IN}class 4 * 1 1 1 1 1 1 1")).contains(&next_line));
+        assert!(error(&format!("{BUILT_IN}class 1 * 1 1 1 1 1 1 1")).contains("twice"));
+        assert!(error(&format!("{BUILT_IN}class 1 x 1 9 1 1 1 1 1")).contains("range"));
+        assert!(error("asteroid").contains("line 1"));
+        assert!(error(&format!("{BUILT_IN}variant asteroid_0 gold 1")).contains("twice"));
+        assert!(error(&format!("{BUILT_IN}variant x lava 1")).contains("lava"));
+        assert!(error(&format!("{BUILT_IN}variant x gold -1")).contains("negative"));
+    }
+
+    #[test]
+    fn test_variants() {
+        let classes = AsteroidClasses::default();
+        assert_eq!(classes.kind("asteroid_1"), VariantKind::Explosive);
+        assert_eq!(classes.weight("asteroid_0"), 85.0);
+        assert_eq!(classes.get(3, "asteroid_2").hit_points, 3);
+        assert_eq!(classes.get(3, "asteroid_3").splits, 3);
+        assert!(classes.get(1, "asteroid_4").score > classes.get(1, "asteroid_0").score);
+
+        // Une texture absente de la table donne un astéroïde commun
+        assert_eq!(classes.kind("asteroid_9"), VariantKind::Common);
+        assert_eq!(classes.weight("asteroid_9"), 1.0);
+    }
+}
@@ -0,0 +1,423 @@
+use ::rand::rngs::SmallRng;
+use macroquad::math::Vec2;
+
+use crate::asteroid::Asteroid;
+use crate::audio::SoundEvent;
+use crate::classes::AsteroidClasses;
+use crate::general::{Gamestate, TICKS};
+use crate::grid::Grid;
+use crate::spaceship::Spaceship;
+
+/// Will detect collisions between the spaceship and asteroids and
+/// execute necessary operations to match the expected behaviour.
+/// The whole path of the spaceship during the tick is tested.
+/// Returns the index of the asteroid that was hit, which loses a hit point,
+/// and whether it broke: it is then left inactive with its fragments added
+/// until the broken asteroids are removed.
+pub fn check_collision_spaceship_asteroid(
+    spaceship: &Spaceship,
+    asteroids: &mut Vec<Asteroid>,
+    grid: &Grid,
+    rng: &mut SmallRng,
+    classes: &AsteroidClasses,
+) -> Option<(usize, bool)> {
+    let hull = spaceship.hull();
+    let radius = spaceship.get_collision_radius();
+    let area = swept_area(
+        spaceship.position,
+        spaceship.velocity * TICKS as f32,
+        radius,
+        drift(asteroids),
+    );
+    break_first(asteroids, grid, area, true, rng, classes, |asteroid| {
+        let motion = (spaceship.velocity - asteroid.velocity) * TICKS as f32;
+        // The hull only matters against the outline of an asteroid, and is
+        // moved along its path by steps shorter than itself
+        if asteroid.shape.is_empty() {
+            return asteroid.sweeps_circle(grid.world(), spaceship.position, motion, radius);
+        }
+        let steps = (motion.length() / radius).ceil().max(1.0) as usize;
+        (0..=steps).any(|step| {
+            let offset = motion * step as f32 / steps as f32;
+            let hull = hull.map(|point| point + offset);
+            asteroid.hits_polygon(grid.world(), spaceship.position + offset, &hull)
+        })
+    })
+}
+
+/// Finds the first asteroid touching a circle and hits it. Returns its
+/// index and whether it broke, see [`check_collision_spaceship_asteroid`].
+pub fn hit_asteroid(
+    position: Vec2,
+    radius: f32,
+    asteroids: &mut Vec<Asteroid>,
+    grid: &Grid,
+    can_add: bool,
+    rng: &mut SmallRng,
+    classes: &AsteroidClasses,
+) -> Option<(usize, bool)> {
+    break_first(
+        asteroids,
+        grid,
+        (position, radius),
+        can_add,
+        rng,
+        classes,
+        |asteroid| asteroid.hits_circle(grid.world(), position, radius),
+    )
+}
+
+// The farthest an asteroid moves during a tick
+fn drift(asteroids: &[Asteroid]) -> f32 {
+    asteroids
+        .iter()
+        .map(|asteroid| asteroid.velocity.length())
+        .fold(0.0, f32::max)
+        * TICKS as f32
+}
+
+// The circle holding the path of a circle moving by `motion` during the
+// tick, widened by the drift of the asteroids to find them on the grid
+fn swept_area(position: Vec2, motion: Vec2, radius: f32, drift: f32) -> (Vec2, f32) {
+    (
+        position + motion / 2.0,
+        radius + motion.length() / 2.0 + drift,
+    )
+}
+
+// Takes a hit point from the first asteroid of the grid touched in the
+// area, and breaks it once it has none left, setting off its blast if it is
+// explosive
+fn break_first(
+    asteroids: &mut Vec<Asteroid>,
+    grid: &Grid,
+    (position, radius): (Vec2, f32),
+    can_add: bool,
+    rng: &mut SmallRng,
+    classes: &AsteroidClasses,
+    touches: impl Fn(&Asteroid) -> bool,
+) -> Option<(usize, bool)> {
+    let index = grid
+        .query(position, radius)
+        .into_iter()
+        .find(|index| asteroids[*index].active && touches(&asteroids[*index]))?;
+    let asteroid = &mut asteroids[index];
+    asteroid.hit_points = asteroid.hit_points.saturating_sub(1);
+    let broken = asteroid.hit_points == 0;
+    if broken {
+        break_asteroid(index, asteroids, grid, can_add, rng, classes);
+    }
+    Some((index, broken))
+}
+
+// Splits the asteroid and leaves it inactive, setting off its blast if it
+// is explosive
+fn break_asteroid(
+    index: usize,
+    asteroids: &mut Vec<Asteroid>,
+    grid: &Grid,
+    can_add: bool,
+    rng: &mut SmallRng,
+    classes: &AsteroidClasses,
+) {
+    let asteroid = &mut asteroids[index];
+    let to_add = asteroid.split(can_add, rng, classes);
+    asteroid.active = false;
+    if asteroid.is_explosive(classes) {
+        let (position, radius) = (asteroid.position, asteroid.blast_radius());
+        explode(position, radius, asteroids, grid, can_add, rng, classes);
+    }
+    asteroids.extend(to_add);
+}
+
+/// Sets off the blast of an explosive asteroid: every asteroid of the grid
+/// within reach loses a hit point, and the explosive ones that break blast
+/// in turn. The fragments are not in the grid, so an asteroid is never
+/// caught in its own blast. Returns the points of the broken asteroids.
+pub fn explode(
+    position: Vec2,
+    radius: f32,
+    asteroids: &mut Vec<Asteroid>,
+    grid: &Grid,
+    can_add: bool,
+    rng: &mut SmallRng,
+    classes: &AsteroidClasses,
+) -> u128 {
+    let mut score = 0;
+    let mut blasts = vec![(position, radius)];
+
+    while let Some((position, radius)) = blasts.pop() {
+        for index in grid.query(position, radius) {
+            let asteroid = &mut asteroids[index];
+            if !asteroid.active || !asteroid.hits_circle(grid.world(), position, radius) {
+                continue;
+            }
+            asteroid.hit_points = asteroid.hit_points.saturating_sub(1);
+            if asteroid.hit_points == 0 {
+                let to_add = asteroid.split(can_add, rng, classes);
+                asteroid.active = false;
+                score += classes.get(asteroid.size, &asteroid.variant()).score;
+                if asteroid.is_explosive(classes) {
+                    blasts.push((asteroid.position, asteroid.blast_radius()));
+                }
+                asteroids.extend(to_add);
+            }
+        }
+    }
+    score
+}
+
+/// Will detect collisions of the flying saucers and their missiles with the
+/// missiles of the player, the asteroids and the spaceship.
+/// The missiles and the spaceship only look at the saucers of the grid
+/// around them, as with the asteroids.
+pub fn check_collision_ufo(gamestate: &mut Gamestate) {
+    let world = gamestate.world;
+    gamestate
+        .asteroid_grid
+        .rebuild(&gamestate.world, &gamestate.asteroids);
+    gamestate.ufo_grid.clear(&world);
+    for (index, ufo) in gamestate.ufos.iter().enumerate() {
+        if ufo.active {
+            gamestate
+                .ufo_grid
+                .insert(index, ufo.position, ufo.kind.radius());
+        }
+    }
+
+    // Shot down by the player
+    for missile in gamestate
+        .missiles
+        .iter_mut()
+        .filter(|missile| missile.active)
+    {
+        let Some(index) = gamestate
+            .ufo_grid
+            .query(missile.position, missile.size)
+            .into_iter()
+            .find(|index| {
+                let ufo = &gamestate.ufos[*index];
+                ufo.active
+                    && world.distance(missile.position, ufo.position)
+                        < ufo.kind.radius() + missile.size
+            })
+        else {
+            continue;
+        };
+        let kind = gamestate.ufos[index].kind;
+        missile.active = false;
+        gamestate.ufos[index].active = false;
+        gamestate.score += kind.score();
+        gamestate
+            .sound_events
+            .push(SoundEvent::Explosion(kind.size()));
+    }
+
+    // Crashed into an asteroid
+    for index in 0..gamestate.ufos.len() {
+        let ufo = &gamestate.ufos[index];
+        let (position, radius, kind) = (ufo.position, ufo.kind.radius(), ufo.kind);
+        if !ufo.active {
+            continue;
+        }
+        let can_add = gamestate.asteroids.len() < gamestate.asteroid_limit;
+        if hit_asteroid(
+            position,
+            radius,
+            &mut gamestate.asteroids,
+            &gamestate.asteroid_grid,
+            can_add,
+            &mut gamestate.rng,
+            &gamestate.asteroid_classes,
+        )
+        .is_some()
+        {
+            gamestate.ufos[index].active = false;
+            gamestate
+                .sound_events
+                .push(SoundEvent::Explosion(kind.size()));
+        }
+    }
+
+    // Rammed by the spaceship, which earns the points
+    let (position, radius) = (
+        gamestate.spaceship.position,
+        gamestate.spaceship.get_collision_radius(),
+    );
+    for index in gamestate.ufo_grid.query(position, radius) {
+        let ufo = &gamestate.ufos[index];
+        let kind = ufo.kind;
+        if !ufo.active
+            || gamestate.is_respawning()
+            || gamestate.spaceship.is_invulnerable()
+            || world.distance(position, ufo.position) >= kind.radius() + radius
+        {
+            continue;
+        }
+        gamestate.ufos[index].active = false;
+        gamestate.score += kind.score();
+        gamestate
+            .sound_events
+            .push(SoundEvent::Explosion(kind.size()));
+        let speed = gamestate.ufos[index].velocity.length();
+        let damage = gamestate.shield_config.damage(kind.size(), speed);
+        gamestate.hit_spaceship(damage);
+    }
+
+    for index in 0..gamestate.ufo_missiles.len() {
+        let missile = &gamestate.ufo_missiles[index];
+        let (position, size) = (missile.position, missile.size);
+        if !missile.active {
+            continue;
+        }
+
+        let spaceship = &gamestate.spaceship;
+        if !gamestate.is_respawning()
+            && !spaceship.is_invulnerable()
+            && world.distance(spaceship.position, position)
+                < size + spaceship.get_collision_radius()
+        {
+            gamestate.ufo_missiles[index].active = false;
+            let speed = gamestate.ufo_missiles[index].velocity.length();
+            let damage = gamestate.shield_config.damage(1, speed);
+            gamestate.hit_spaceship(damage);
+            continue;
+        }
+
+        // The saucers also break the asteroids, without scoring
+        let can_add = gamestate.asteroids.len() < gamestate.asteroid_limit;
+        if hit_asteroid(
+            position,
+            size,
+            &mut gamestate.asteroids,
+            &gamestate.asteroid_grid,
+            can_add,
+            &mut gamestate.rng,
+            &gamestate.asteroid_classes,
+        )
+        .is_some()
+        {
+            gamestate.ufo_missiles[index].active = false;
+        }
+    }
+    gamestate.remove_broken_asteroids();
+}
+
+/// Will detect collisions between missiles and asteroids and
+/// execute necessary operations to match the expected behaviour.
+/// Each missile only looks at the asteroids of the grid around its path
+/// during the tick, so that a fast missile can't go through an asteroid.
+pub fn check_collision_asteroid_missile(gamestate: &mut Gamestate) {
+    gamestate
+        .asteroid_grid
+        .rebuild(&gamestate.world, &gamestate.asteroids);
+    let can_add = gamestate.number_of_asteroids < gamestate.asteroid_limit;
+    let drift = drift(&gamestate.asteroids);
+    let asteroids = &mut gamestate.asteroids;
+    let classes = &gamestate.asteroid_classes;
+    let mut blasts = Vec::new();
+
+    for missile in gamestate
+        .missiles
+        .iter_mut()
+        .filter(|missile| missile.active)
+    {
+        let motion = missile.velocity * TICKS as f32;
+        let (center, radius) = swept_area(missile.position, motion, missile.size, drift);
+        let Some(index) = gamestate
+            .asteroid_grid
+            .query(center, radius)
+            .into_iter()
+            .find(|index| {
+                let asteroid = &asteroids[*index];
+                let motion = (missile.velocity - asteroid.velocity) * TICKS as f32;
+                asteroid.active
+                    && asteroid.sweeps_circle(
+                        &gamestate.world,
+                        missile.position,
+                        motion,
+                        missile.size,
+                    )
+            })
+        else {
+            continue;
+        };
+        missile.active = false;
+
+        // Some asteroids need several hits before breaking
+        let asteroid = &mut asteroids[index];
+        asteroid.hit_points = asteroid.hit_points.saturating_sub(1);
+        if asteroid.hit_points == 0 {
+            let to_add = asteroid.split(can_add, &mut gamestate.rng, classes);
+            asteroid.active = false;
+            // The score gained depends on the class of the asteroid
+            gamestate.score += classes.get(asteroid.size, &asteroid.variant()).score;
+            if asteroid.is_explosive(classes) {
+                blasts.push((asteroid.position, asteroid.blast_radius()));
+            }
+            asteroids.extend(to_add);
+        }
+    }
+
+    // The player also earns the asteroids broken by the blasts
+    for (position, radius) in blasts {
+        gamestate.score += explode(
+            position,
+            radius,
+            asteroids,
+            &gamestate.asteroid_grid,
+            can_add,
+            &mut gamestate.rng,
+            classes,
+        );
+    }
+
+    // Enlève les missiles et les astéroïdes détruits
+    gamestate.missiles.retain(|missile| missile.active);
+    gamestate.remove_broken_asteroids();
+}
+
+/// Will detect collisions between asteroids and make them bounce off each
+/// other, see [`PhysicsConfig`](crate::physics::PhysicsConfig). The
+/// asteroids of an impact hard enough both break, without scoring.
+pub fn check_collision_asteroids(gamestate: &mut Gamestate) {
+    gamestate
+        .asteroid_grid
+        .rebuild(&gamestate.world, &gamestate.asteroids);
+    let can_add = gamestate.number_of_asteroids < gamestate.asteroid_limit;
+    let world = gamestate.world;
+    let asteroids = &mut gamestate.asteroids;
+
+    // Each pair is seen once, from the asteroid with the lowest index
+    for index in 0..asteroids.len() {
+        let asteroid = &asteroids[index];
+        if !asteroid.active {
+            continue;
+        }
+        let candidates = gamestate
+            .asteroid_grid
+            .query(asteroid.position, asteroid.radius());
+        for other in candidates.into_iter().filter(|other| *other > index) {
+            let (first, second) = asteroids.split_at_mut(other);
+            let (first, second) = (&mut first[index], &mut second[0]);
+            if !first.active || !second.active || !first.touches(&world, second) {
+                continue;
+            }
+            let energy = gamestate.physics_config.bounce(&world, first, second);
+            if gamestate.physics_config.breaks(energy) {
+                for broken in [index, other] {
+                    break_asteroid(
+                        broken,
+                        asteroids,
+                        &gamestate.asteroid_grid,
+                        can_add,
+                        &mut gamestate.rng,
+                        &gamestate.asteroid_classes,
+                    );
+                }
+            }
+        }
+    }
+
+    gamestate.remove_broken_asteroids();
+}
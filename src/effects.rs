@@ -0,0 +1,225 @@
+use ::rand::rngs::SmallRng;
+use ::rand::{Rng, SeedableRng};
+use macroquad::prelude::*;
+
+use crate::audio::SoundEvent;
+use crate::general::Gamestate;
+
+/// Which effects of the camera are on, each of them can be turned off for
+/// the players sensitive to motion, and how strong they are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EffectsConfig {
+    pub shake: bool,
+    pub hit_stop: bool,
+    pub flash: bool,
+    /// Pixels of shake for each size of what exploded.
+    pub shake_per_size: f32,
+    pub max_shake: f32,
+    /// Pixels of shake lost each second.
+    pub shake_decay: f32,
+    /// Frames frozen when the spaceship is destroyed.
+    pub hit_stop_frames: u32,
+    /// Seconds the red flash takes to fade.
+    pub flash_duration: f32,
+}
+
+impl Default for EffectsConfig {
+    fn default() -> Self {
+        Self {
+            shake: true,
+            hit_stop: true,
+            flash: true,
+            shake_per_size: 3.0,
+            max_shake: 14.0,
+            shake_decay: 30.0,
+            hit_stop_frames: 6,
+            flash_duration: 0.6,
+        }
+    }
+}
+
+/// The effects drawn over the game: the screen shakes with the explosions,
+/// and the destruction of the spaceship freezes the game for a few frames
+/// while the edges of the screen flash red.
+///
+/// They follow the real time rather than the simulation, and their random
+/// offsets never touch the random values of the game.
+pub struct CameraEffects {
+    pub config: EffectsConfig,
+    // Pixels of the current shake, frames left frozen and seconds left of flash
+    shake: f32,
+    hit_stop: u32,
+    flash: f32,
+    was_respawning: bool,
+    rng: SmallRng,
+}
+
+impl Default for CameraEffects {
+    fn default() -> Self {
+        Self::new(EffectsConfig::default())
+    }
+}
+
+impl CameraEffects {
+    pub fn new(config: EffectsConfig) -> Self {
+        Self {
+            config,
+            shake: 0.0,
+            hit_stop: 0,
+            flash: 0.0,
+            was_respawning: false,
+            rng: SmallRng::seed_from_u64(0),
+        }
+    }
+
+    /// Starts the effects of the last update of the game, and fades the
+    /// current ones over a frame of `frame_time` seconds.
+    pub fn update(&mut self, gamestate: &Gamestate, frame_time: f32) {
+        self.shake = (self.shake - self.config.shake_decay * frame_time).max(0.0);
+        self.flash = (self.flash - frame_time).max(0.0);
+        self.hit_stop = self.hit_stop.saturating_sub(1);
+
+        for event in &gamestate.sound_events {
+            if let SoundEvent::Explosion(size) = event {
+                self.shake = (self.shake + self.config.shake_per_size * *size as f32)
+                    .min(self.config.max_shake);
+            }
+        }
+
+        // The spaceship was just destroyed
+        let respawning = gamestate.game_started && gamestate.is_respawning();
+        if respawning && !self.was_respawning {
+            self.hit_stop = self.config.hit_stop_frames;
+            self.flash = self.config.flash_duration;
+        }
+        self.was_respawning = respawning;
+    }
+
+    /// Whether the game must stay frozen during this frame.
+    pub fn is_frozen(&self) -> bool {
+        self.config.hit_stop && self.hit_stop > 0
+    }
+
+    /// Pixels the view is moved by the shake during this frame.
+    pub fn offset(&mut self) -> Vec2 {
+        if !self.config.shake || self.shake <= 0.0 {
+            return Vec2::ZERO;
+        }
+        let direction = Vec2::from_angle(self.rng.gen_range(0.0..std::f32::consts::TAU));
+        direction * self.rng.gen_range(0.0..=self.shake)
+    }
+
+    /// The camera of the playfield for this frame, moved by the shake.
+    pub fn camera(&mut self, width: f32, height: f32) -> Camera2D {
+        let offset = self.offset();
+        Camera2D::from_display_rect(Rect::new(offset.x, offset.y, width, height))
+    }
+
+    /// Strength of the red flash from 0 to 1.
+    pub fn flash(&self) -> f32 {
+        if !self.config.flash || self.config.flash_duration <= 0.0 {
+            return 0.0;
+        }
+        self.flash / self.config.flash_duration
+    }
+
+    /// Draws the red flash along the edges of the screen, fading toward the
+    /// center.
+    pub fn draw_flash(&self, width: f32, height: f32) {
+        let strength = self.flash();
+        if strength <= 0.0 {
+            return;
+        }
+        let bands = 12;
+        let band = width.min(height) / 40.0;
+        for index in 0..bands {
+            let color = Color::new(
+                0.9,
+                0.0,
+                0.0,
+                0.35 * strength * (1.0 - index as f32 / bands as f32),
+            );
+            let inset = index as f32 * band;
+            let (inner_width, inner_height) = (width - 2.0 * inset, height - 2.0 * inset);
+            draw_rectangle(inset, inset, inner_width, band, color);
+            draw_rectangle(inset, height - inset - band, inner_width, band, color);
+            draw_rectangle(inset, inset + band, band, inner_height - 2.0 * band, color);
+            draw_rectangle(
+                width - inset - band,
+                inset + band,
+                band,
+                inner_height - 2.0 * band,
+                color,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::World;
+
+    fn destroyed_spaceship() -> Gamestate {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.new_game();
+        gamestate.update(0.0);
+        gamestate.destroy_spaceship();
+        gamestate
+    }
+
+    #[test]
+    fn test_shake_scales_with_size() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        let mut effects = CameraEffects::default();
+        gamestate.sound_events.push(SoundEvent::Explosion(1));
+        effects.update(&gamestate, 0.0);
+        let small = effects.shake;
+        gamestate.sound_events = vec![SoundEvent::Explosion(3)];
+        let mut large = CameraEffects::default();
+        large.update(&gamestate, 0.0);
+        assert!(large.shake > small);
+        assert!(large.offset().length() <= large.shake);
+
+        // Elle s'estompe, et plusieurs explosions ne dépassent pas le maximum
+        gamestate.sound_events = vec![SoundEvent::Explosion(3); 10];
+        large.update(&gamestate, 0.0);
+        assert_eq!(large.shake, large.config.max_shake);
+        gamestate.sound_events.clear();
+        large.update(&gamestate, 10.0);
+        assert_eq!(large.offset(), Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_spaceship_destroyed() {
+        let gamestate = destroyed_spaceship();
+        let mut effects = CameraEffects::default();
+        effects.update(&gamestate, 1.0 / 60.0);
+        assert!(effects.flash() > 0.0);
+
+        // Figé quelques images, pas davantage
+        let mut frozen = 0;
+        while effects.is_frozen() {
+            frozen += 1;
+            effects.update(&gamestate, 1.0 / 60.0);
+        }
+        assert_eq!(frozen, effects.config.hit_stop_frames);
+    }
+
+    #[test]
+    fn test_effects_can_be_turned_off() {
+        let mut gamestate = destroyed_spaceship();
+        let mut effects = CameraEffects::new(EffectsConfig {
+            shake: false,
+            hit_stop: false,
+            flash: false,
+            ..EffectsConfig::default()
+        });
+        effects.update(&gamestate, 0.0);
+        assert!(!effects.is_frozen());
+        assert_eq!(effects.flash(), 0.0);
+        gamestate.sound_events.push(SoundEvent::Explosion(3));
+        effects.update(&gamestate, 0.0);
+        assert_eq!(effects.offset(), Vec2::ZERO);
+    }
+}
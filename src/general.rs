@@ -1,41 +1,1385 @@
-use crate::asteroid::Asteroid;
-use crate::missile::Missile;
-use crate::spaceship::Spaceship;
+use ::rand::rngs::SmallRng;
+use ::rand::{Rng, SeedableRng};
+use macroquad::color::{LIME, RED};
+use macroquad::math::{vec2, Vec2};
+
+use crate::asteroid::{line_color, Asteroid};
+use crate::audio::SoundEvent;
+use crate::classes::AsteroidClasses;
+use crate::collision::{
+    check_collision_asteroid_missile, check_collision_asteroids,
+    check_collision_spaceship_asteroid, check_collision_ufo,
+};
+use crate::grid::Grid;
+use crate::input::Input;
+use crate::level::LevelConfig;
+use crate::missile::{Missile, MissileConfig};
+use crate::particles::Particles;
+use crate::physics::PhysicsConfig;
+use crate::replay::{Playback, Replay, ReplayError};
+use crate::shape::ShapeConfig;
+use crate::spaceship::{HyperspaceConfig, RespawnConfig, ShieldConfig, Spaceship};
+use crate::ufo::{Ufo, UfoConfig};
+use crate::world::World;
+
+// The precision of the simulation / the duration of one tick
+pub const TICKS: f64 = 1.0 / 60.0;
+
+/// How many lives the player has and how more are earned.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LivesConfig {
+    pub starting_lives: u8,
+    pub max_lives: u8,
+    /// Score between two extra lives, `None` to never give any.
+    pub extra_life_every: Option<u128>,
+    /// Seconds the extra life is announced on screen.
+    pub notice_duration: f32,
+}
+
+impl Default for LivesConfig {
+    fn default() -> Self {
+        Self {
+            starting_lives: 3,
+            max_lives: 5,
+            extra_life_every: Some(10_000),
+            notice_duration: 2.0,
+        }
+    }
+}
 
 pub struct Gamestate {
     pub delta_time: f64,
+    pub accumulator: f64,
     pub simulation_speed: f64,
     pub debug: bool,
     pub loop_number: u128,
+    pub world: World,
+    pub seed: u64,
+    pub rng: SmallRng,
     pub asteroids: Vec<Asteroid>,
+    pub asteroid_classes: AsteroidClasses,
+    pub shape_config: ShapeConfig,
+    // Broadphase of the collisions with the asteroids, rebuilt before each check
+    pub asteroid_grid: Grid,
+    pub physics_config: PhysicsConfig,
     pub missiles: Vec<Missile>,
     pub spaceship: Spaceship,
+    pub shield_config: ShieldConfig,
+    pub missile_config: MissileConfig,
+    pub hyperspace_config: HyperspaceConfig,
+    pub respawn_config: RespawnConfig,
+    // Seconds since the spaceship was destroyed, while waiting for a safe place
+    pub respawn_wait: Option<f32>,
+    // Flying saucers, their missiles and the seconds before the next one
+    pub ufos: Vec<Ufo>,
+    pub ufo_missiles: Vec<Missile>,
+    pub ufo_config: UfoConfig,
+    pub ufo_timer: f32,
+    // Broadphase of the collisions with the saucers, rebuilt before each check
+    pub ufo_grid: Grid,
+    // Debris, exhaust and trails, only drawn
+    pub particles: Particles,
     pub asteroid_limit: usize,
     pub number_of_asteroids: usize,
+    // Asteroids at the start of the current wave, for the heartbeat
+    pub wave_size: usize,
     pub lives: u8,
+    pub lives_config: LivesConfig,
+    // Score of the next extra life, and seconds left to announce the last one
+    pub next_extra_life: u128,
+    pub extra_life_notice: f32,
     pub score: u128,
     pub game_started: bool,
     pub game_over: bool,
     pub game_won: bool,
+    // The current wave, and the seconds left before the next one
+    pub level: u32,
+    pub intermission: f32,
+    pub level_config: LevelConfig,
+    pub input: Input,
+    // The game being recorded, and the last recorded game ready to be saved
+    pub recording: Option<Replay>,
+    pub finished_replay: Option<Replay>,
+    // The replay being played, and whether the last playback matched its recording
+    pub playback: Option<Playback>,
+    pub playback_result: Option<Result<(), ReplayError>>,
+    // What was heard during the last update, until the audio plays it
+    pub sound_events: Vec<SoundEvent>,
 }
 
 impl Gamestate {
-    pub fn new() -> Gamestate {
+    /// Create a new state. Every random value of the simulation comes from
+    /// `rng`, seeded from `seed` at the start of each game.
+    pub fn new(world: World, seed: u64) -> Gamestate {
         Gamestate {
             delta_time: 0.0,
+            accumulator: 0.0,
             simulation_speed: 1.0,
             debug: false,
             loop_number: 0,
+            world,
+            seed,
+            rng: SmallRng::seed_from_u64(seed),
             asteroids: Vec::new(),
+            asteroid_classes: AsteroidClasses::default(),
+            shape_config: ShapeConfig::default(),
+            asteroid_grid: Grid::default(),
+            physics_config: PhysicsConfig::default(),
             missiles: Vec::new(),
-            spaceship: Spaceship::new(),
+            spaceship: Spaceship::new(&world),
+            shield_config: ShieldConfig::default(),
+            missile_config: MissileConfig::default(),
+            hyperspace_config: HyperspaceConfig::default(),
+            respawn_config: RespawnConfig::default(),
+            respawn_wait: None,
+            ufos: Vec::new(),
+            ufo_missiles: Vec::new(),
+            ufo_config: UfoConfig::default(),
+            ufo_timer: UfoConfig::default().spawn_interval,
+            ufo_grid: Grid::default(),
+            particles: Particles::default(),
             asteroid_limit: 26,
+            wave_size: 0,
             number_of_asteroids: 0,
-            lives: 3,
+            lives: LivesConfig::default().starting_lives,
+            lives_config: LivesConfig::default(),
+            next_extra_life: LivesConfig::default().extra_life_every.unwrap_or(0),
+            extra_life_notice: 0.0,
             score: 0,
             game_started: false,
             game_over: false,
             game_won: false,
+            level: 1,
+            intermission: 0.0,
+            level_config: LevelConfig::default(),
+            input: Input::default(),
+            recording: None,
+            finished_replay: None,
+            playback: None,
+            playback_result: None,
+            sound_events: Vec::new(),
+        }
+    }
+
+    /// Resets the state, spawns a new asteroid field from the seed and
+    /// starts recording the game.
+    pub fn new_game(&mut self) {
+        self.rng = SmallRng::seed_from_u64(self.seed);
+        let mut recording = Replay::new(self.seed, self.world);
+        recording.vector = self.shape_config.enabled;
+        recording.bounce = self.physics_config.enabled;
+        recording.campaign = self.level_config.campaign;
+        recording.classes = self.asteroid_classes.fingerprint();
+        self.recording = Some(recording);
+        self.playback = None;
+        self.loop_number = 0;
+        self.game_started = true;
+        self.lives = self.lives_config.starting_lives; // Réinitialise les vies
+        self.next_extra_life = self.lives_config.extra_life_every.unwrap_or(0);
+        self.extra_life_notice = 0.0;
+        self.score = 0; // Réinitialise le score
+        self.game_over = false;
+        self.game_won = false;
+        self.accumulator = 0.0;
+        self.input = Input::default();
+        self.asteroids.clear();
+        self.missiles.clear();
+        self.ufos.clear();
+        self.ufo_missiles.clear();
+        self.ufo_timer = self.ufo_config.spawn_interval;
+        self.particles.clear();
+        self.respawn_spaceship();
+        self.respawn_wait = None;
+        self.level = 1;
+        self.intermission = 0.0;
+        self.start_wave();
+    }
+
+    /// Spawns the asteroids of the current level.
+    pub fn start_wave(&mut self) {
+        let wave = self.level_config.wave(self.level);
+        for _ in 0..wave.count {
+            let mut asteroid = Asteroid::new_random_away(
+                &self.world,
+                &mut self.rng,
+                &self.asteroid_classes,
+                self.spaceship.position,
+                self.level_config.min_spawn_distance,
+            );
+            asteroid.velocity *= wave.speed_factor;
+            if self.shape_config.enabled {
+                asteroid.shape = self.shape_config.random_outline(&mut self.rng);
+            }
+            self.asteroids.push(asteroid);
+        }
+        self.number_of_asteroids = self.asteroids.len();
+        self.wave_size = self.asteroids.len();
+    }
+
+    /// Advances the simulation by `frame_time` seconds of real time.
+    ///
+    /// The time is scaled by the simulation speed and consumed in fixed
+    /// steps of [`TICKS`], so the game behaves the same at any frame rate.
+    /// What remains in the accumulator is used by [`Gamestate::alpha`] to
+    /// interpolate the rendering between the last two ticks.
+    ///
+    /// Nothing here needs a window, only the [`World`] carried by the state,
+    /// which allows the game logic to run headlessly. The sounds of the
+    /// previous update which were not played are dropped.
+    pub fn update(&mut self, frame_time: f64) {
+        self.sound_events.clear();
+        self.delta_time = frame_time * self.simulation_speed;
+        self.accumulator += self.delta_time;
+        while self.accumulator >= TICKS {
+            self.tick();
+            self.accumulator -= TICKS;
+        }
+    }
+
+    /// Puts a new spaceship at the center with a full shield, protected
+    /// for a moment.
+    pub fn respawn_spaceship(&mut self) {
+        self.spaceship = Spaceship::new(&self.world);
+        self.spaceship.shield = self.shield_config.max_shield;
+        self.spaceship.invulnerability = self.shield_config.invulnerability_duration;
+    }
+
+    /// Lowers the shield of the spaceship. It is destroyed when its shield
+    /// was already down.
+    pub fn hit_spaceship(&mut self, damage: f32) {
+        if self.spaceship.take_hit(damage, &self.shield_config) {
+            self.destroy_spaceship();
+        }
+    }
+
+    /// Takes a life and removes the spaceship until a safe place is found.
+    pub fn destroy_spaceship(&mut self) {
+        self.lives = self.lives.saturating_sub(1);
+        self.sound_events.push(SoundEvent::Explosion(3));
+        self.particles
+            .burst(self.spaceship.position, self.spaceship.velocity);
+        self.respawn_wait = Some(0.0);
+    }
+
+    /// Gives a life for every score milestone reached, up to the maximum,
+    /// and counts down the announcement of the last one.
+    fn award_extra_lives(&mut self, delta_time: f64) {
+        self.extra_life_notice = (self.extra_life_notice - delta_time as f32).max(0.0);
+        let Some(every) = self
+            .lives_config
+            .extra_life_every
+            .filter(|every| *every > 0)
+        else {
+            return;
+        };
+        while self.score >= self.next_extra_life {
+            // A milestone reached with all the lives is lost
+            if self.lives < self.lives_config.max_lives {
+                self.lives += 1;
+                self.extra_life_notice = self.lives_config.notice_duration;
+                self.sound_events.push(SoundEvent::ExtraLife);
+            }
+            self.next_extra_life += every;
+        }
+    }
+
+    /// Removes the asteroids broken by the collisions, each with its
+    /// explosion and debris but for the leftovers without size.
+    pub fn remove_broken_asteroids(&mut self) {
+        for asteroid in &self.asteroids {
+            if !asteroid.active && asteroid.size > 0 {
+                self.sound_events.push(SoundEvent::Explosion(asteroid.size));
+                let kind = self.asteroid_classes.kind(&asteroid.variant());
+                self.particles.explosion(
+                    asteroid.position,
+                    asteroid.velocity,
+                    asteroid.size,
+                    line_color(kind),
+                );
+            }
+        }
+        self.asteroids.retain(|asteroid| asteroid.active);
+        self.number_of_asteroids = self.asteroids.len();
+    }
+
+    /// Returns whether the spaceship is out of the game, waiting to respawn.
+    pub fn is_respawning(&self) -> bool {
+        self.respawn_wait.is_some()
+    }
+
+    /// Returns whether no asteroid nor saucer is within the safe radius of
+    /// a position.
+    pub fn is_clear(&self, position: Vec2) -> bool {
+        let safe_radius = self.respawn_config.safe_radius;
+        self.asteroids.iter().all(|asteroid| {
+            self.world.distance(asteroid.position, position) >= safe_radius + asteroid.radius()
+        }) && self.ufos.iter().all(|ufo| {
+            self.world.distance(ufo.position, position) >= safe_radius + ufo.kind.radius()
+        })
+    }
+
+    /// The clear place of the world closest to `position`, searched on a
+    /// grid of half the safe radius.
+    pub fn nearest_clear_spot(&self, position: Vec2) -> Option<Vec2> {
+        let step = (self.respawn_config.safe_radius / 2.0).max(1.0);
+        let columns = (self.world.width / step) as u32;
+        let rows = (self.world.height / step) as u32;
+        (0..=columns)
+            .flat_map(|column| (0..=rows).map(move |row| vec2(column as f32, row as f32) * step))
+            .filter(|spot| self.is_clear(*spot))
+            .min_by(|a, b| {
+                self.world
+                    .distance(*a, position)
+                    .total_cmp(&self.world.distance(*b, position))
+            })
+    }
+
+    /// Brings the spaceship back at the center once it is clear, or at the
+    /// nearest clear place when the center stays crowded for too long.
+    fn update_respawn(&mut self, delta_time: f64) {
+        let Some(waited) = self.respawn_wait else {
+            return;
+        };
+        let waited = waited + delta_time as f32;
+        let center = self.world.center();
+        let spot = if self.is_clear(center) {
+            Some(center)
+        } else if waited >= self.respawn_config.max_wait {
+            self.nearest_clear_spot(center)
+        } else {
+            None
+        };
+        match spot {
+            Some(spot) => {
+                self.respawn_spaceship();
+                self.spaceship.position = spot;
+                self.spaceship.save_previous();
+                self.respawn_wait = None;
+            }
+            None => self.respawn_wait = Some(waited),
+        }
+    }
+
+    /// Sends a flying saucer from time to time, moves the saucers and
+    /// lets them fire.
+    fn update_ufos(&mut self, delta_time: f64) {
+        // The next saucer is only awaited once the last one is gone
+        if self.ufos.is_empty() {
+            self.ufo_timer -= delta_time as f32;
+        }
+        if self.ufo_timer <= 0.0 && self.ufos.is_empty() {
+            self.ufo_timer = self.ufo_config.spawn_interval;
+            self.ufos.push(Ufo::new_random(
+                &self.world,
+                &self.ufo_config,
+                &mut self.rng,
+            ));
+        }
+
+        // Nothing to aim at until the spaceship is back
+        let target = self.spaceship.position;
+        let respawning = self.is_respawning();
+        for ufo in &mut self.ufos {
+            ufo.update(delta_time, &self.world, &self.ufo_config, &mut self.rng);
+            if respawning {
+                continue;
+            }
+            if let Some(missile) = ufo.fire(target, &self.world, &self.ufo_config, &mut self.rng) {
+                self.ufo_missiles.push(missile);
+            }
+        }
+    }
+
+    /// Sends the spaceship to a random place of the world, drawn from the
+    /// game's rng. The jump may fail and destroy the spaceship.
+    pub fn hyperspace(&mut self) {
+        let position = vec2(
+            self.rng.gen_range(0.0..self.world.width),
+            self.rng.gen_range(0.0..self.world.height),
+        );
+        if self.rng.gen::<f32>() < self.hyperspace_config.failure_chance {
+            self.destroy_spaceship();
+        } else {
+            self.spaceship.jump(position, &self.hyperspace_config);
+        }
+    }
+
+    /// Starts a game that plays the inputs of a replay instead of the player's,
+    /// in the modes it was recorded in. A replay recorded with other asteroid
+    /// classes cannot be played and leaves the state untouched.
+    pub fn start_playback(&mut self, replay: Replay) -> Result<(), ReplayError> {
+        let classes = self.asteroid_classes.fingerprint();
+        if replay.classes != classes {
+            return Err(ReplayError::Classes {
+                expected: replay.classes,
+                found: classes,
+            });
+        }
+        self.seed = replay.seed;
+        self.world = replay.world;
+        self.shape_config.enabled = replay.vector;
+        self.physics_config.enabled = replay.bounce;
+        self.level_config.campaign = replay.campaign;
+        self.new_game();
+        self.recording = None;
+        self.playback = Some(Playback::new(replay));
+        Ok(())
+    }
+
+    /// Stops the recording or the playback once the game is over.
+    ///
+    /// The recorded game is kept in `finished_replay` and the playback is
+    /// checked against its recording in `playback_result`.
+    pub fn end_replay(&mut self) {
+        if let Some(mut replay) = self.recording.take() {
+            replay.finish(self.score, self.loop_number);
+            self.finished_replay = Some(replay);
+        }
+        if let Some(playback) = self.playback.take() {
+            self.playback_result = Some(playback.replay.verify(self.score, self.loop_number));
+        }
+    }
+
+    /// The progress between the previous tick and the current one, in \[0;1].
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / TICKS).clamp(0.0, 1.0) as f32
+    }
+
+    /// Runs one fixed step of the simulation: the input of the player,
+    /// the collisions, the splitting and the movement of every object.
+    pub fn tick(&mut self) {
+        self.loop_number += 1;
+        let delta_time = TICKS;
+
+        // Keep the state of the last tick for the interpolation
+        for asteroid in &mut self.asteroids {
+            asteroid.save_previous();
+        }
+        self.spaceship.save_previous();
+        for missile in self.missiles.iter_mut().chain(&mut self.ufo_missiles) {
+            missile.save_previous();
+        }
+        for ufo in &mut self.ufos {
+            ufo.save_previous();
+        }
+        self.particles.update(delta_time, &self.world);
+
+        self.apply_input(delta_time);
+        self.missiles.retain(|m| m.active);
+        self.ufo_missiles.retain(|m| m.active);
+        self.ufos.retain(|ufo| ufo.active);
+
+        // Jeu principal
+        self.spaceship
+            .update_shield(delta_time, &self.shield_config);
+        self.spaceship.weapon.update(delta_time);
+        self.spaceship.update_hyperspace(delta_time);
+        if !self.is_respawning() && !self.spaceship.is_invulnerable() {
+            self.asteroid_grid.rebuild(&self.world, &self.asteroids);
+            if let Some((index, _)) = check_collision_spaceship_asteroid(
+                &self.spaceship,
+                &mut self.asteroids,
+                &self.asteroid_grid,
+                &mut self.rng,
+                &self.asteroid_classes,
+            ) {
+                let asteroid = &self.asteroids[index];
+                let damage = self
+                    .shield_config
+                    .damage(asteroid.size, asteroid.velocity.length());
+                self.hit_spaceship(damage);
+                self.remove_broken_asteroids();
+            }
+        }
+        self.update_ufos(delta_time);
+        check_collision_ufo(self);
+        self.update_respawn(delta_time);
+
+        self.award_extra_lives(delta_time);
+
+        // Si le joueur perd ses 3 vies, revenir au menu principal
+        if self.lives == 0 {
+            if !self.game_over {
+                self.sound_events.push(SoundEvent::GameOver);
+            }
+            self.game_started = false;
+            self.game_over = true;
+        }
+
+        // Asteroids without size are leftovers, removed with the broken ones
+        for asteroid in &mut self.asteroids {
+            if asteroid.size == 0 {
+                asteroid.active = false;
+            }
+        }
+
+        check_collision_asteroid_missile(self);
+        if self.physics_config.enabled {
+            check_collision_asteroids(self);
+        }
+
+        for asteroid in &mut self.asteroids {
+            asteroid.add_rotation(asteroid.turn_rate * delta_time as f32);
+            asteroid.move_object(delta_time, &self.world);
+        }
+        if !self.is_respawning() {
+            self.spaceship.update(delta_time, &self.world);
+        }
+        for missile in self.missiles.iter_mut().chain(&mut self.ufo_missiles) {
+            missile.update(delta_time, &self.world);
+        }
+        for missile in self.missiles.iter().filter(|missile| missile.active) {
+            self.particles.trail(missile.position, RED);
+        }
+        for missile in self.ufo_missiles.iter().filter(|missile| missile.active) {
+            self.particles.trail(missile.position, LIME);
+        }
+
+        // A cleared wave is followed by a short calm, then by a harder one
+        if self.game_started && self.asteroids.is_empty() && self.intermission <= 0.0 {
+            if self.level_config.is_last(self.level) {
+                self.game_won = true;
+                self.game_started = false;
+            } else {
+                self.level += 1;
+                self.intermission = self.level_config.intermission;
+                if self.intermission <= 0.0 {
+                    self.start_wave();
+                }
+            }
+        } else if self.intermission > 0.0 {
+            self.intermission -= delta_time as f32;
+            if self.intermission <= 0.0 {
+                self.intermission = 0.0;
+                self.start_wave();
+            }
+        }
+
+        // The replay ends with the game, or when its inputs are exhausted
+        if self.playback.as_ref().is_some_and(Playback::is_finished) {
+            self.game_started = false;
+        }
+        if !self.game_started {
+            self.end_replay();
+        }
+    }
+
+    /// Applies the commands of the player, or of the replay being played,
+    /// to the spaceship for one tick.
+    fn apply_input(&mut self, delta_time: f64) {
+        let input = match &mut self.playback {
+            Some(playback) => playback.next_input().unwrap_or_default(),
+            None => self.input,
+        };
+        self.input.consume();
+        if let Some(replay) = &mut self.recording {
+            replay.record(input);
+        }
+        // Nothing to control until the spaceship is back
+        if self.is_respawning() {
+            return;
+        }
+
+        if input.thrust {
+            self.spaceship.move_spaceship(delta_time, true);
+            // Out of the back of the spaceship
+            let heading = Vec2::from_angle(self.spaceship.rotation);
+            self.particles.exhaust(
+                self.spaceship.position - heading * self.spaceship.size / 2.0,
+                self.spaceship.velocity,
+                heading,
+            );
+        }
+        if input.reverse {
+            self.spaceship.move_spaceship(delta_time, false);
+        }
+        if input.rotate_left {
+            self.spaceship
+                .add_rotation(-self.spaceship.turn_rate * delta_time as f32);
+        }
+        if input.rotate_right {
+            self.spaceship
+                .add_rotation(self.spaceship.turn_rate * delta_time as f32);
+        }
+        if input.hyperspace && self.spaceship.can_jump() {
+            self.hyperspace();
+        }
+        let in_flight = self.missiles.iter().filter(|m| m.active).count();
+        if input.fire
+            && !self.spaceship.is_materializing()
+            && self.spaceship.weapon.can_fire(in_flight)
+        {
+            self.spaceship.weapon.fire();
+            // Fired along the heading, carried by the velocity of the spaceship
+            self.sound_events.push(SoundEvent::Fire);
+            self.missiles.push(Missile::new(
+                self.spaceship.position,
+                self.spaceship.velocity,
+                self.spaceship.rotation,
+                &self.missile_config,
+            ))
+        }
+        if input.stop {
+            self.spaceship.velocity = Vec2::ZERO;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ufo::UfoKind;
+    use macroquad::prelude::*;
+
+    /// A still asteroid of the given size.
+    fn still_asteroid(position: Vec2, size: u8) -> Asteroid {
+        Asteroid::new(
+            Some(position),
+            Some(Vec2::ZERO),
+            Some(size),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_new_game_headless() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.new_game();
+
+        assert!(gamestate.game_started);
+        assert_eq!(gamestate.level, 1);
+        assert_eq!(
+            gamestate.asteroids.len(),
+            gamestate.level_config.wave(1).count
+        );
+        assert_eq!(gamestate.spaceship.position, vec2(400.0, 300.0));
+    }
+
+    #[test]
+    fn test_update_headless() {
+        let world = World::new(800.0, 600.0);
+        let mut gamestate = Gamestate::new(world, 0);
+        gamestate.new_game();
+
+        for _ in 0..600 {
+            gamestate.update(TICKS);
+        }
+
+        assert!(gamestate.loop_number >= 600);
+        for asteroid in &gamestate.asteroids {
+            assert!(world.contains(asteroid.position));
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_field() {
+        let mut first = Gamestate::new(World::new(800.0, 600.0), 42);
+        let mut second = Gamestate::new(World::new(800.0, 600.0), 42);
+        first.new_game();
+        second.new_game();
+
+        for _ in 0..300 {
+            first.update(TICKS);
+            second.update(TICKS);
+        }
+
+        assert_eq!(first.asteroids.len(), second.asteroids.len());
+        for (a, b) in first.asteroids.iter().zip(&second.asteroids) {
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.texture, b.texture);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_different_seed_different_field() {
+        let mut first = Gamestate::new(World::new(800.0, 600.0), 1);
+        let mut second = Gamestate::new(World::new(800.0, 600.0), 2);
+        first.new_game();
+        second.new_game();
+
+        assert_ne!(first.asteroids[0].position, second.asteroids[0].position);
+    }
+
+    /// Runs two seconds of a game at the given frame rate with thrust held.
+    fn run_at_fps(fps: f64, simulation_speed: f64) -> Gamestate {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 7);
+        gamestate.new_game();
+        gamestate.simulation_speed = simulation_speed;
+        gamestate.input.thrust = true;
+        gamestate.input.rotate_left = true;
+        while gamestate.loop_number < 120 {
+            gamestate.update(1.0 / fps);
+        }
+        gamestate
+    }
+
+    #[test]
+    fn test_same_physics_at_any_frame_rate() {
+        let reference = run_at_fps(60.0, 1.0);
+        for (fps, simulation_speed) in [(30.0, 1.0), (240.0, 1.0), (60.0, 10.0)] {
+            let gamestate = run_at_fps(fps, simulation_speed);
+            assert_eq!(gamestate.loop_number, reference.loop_number);
+            assert_eq!(gamestate.spaceship.position, reference.spaceship.position);
+            assert_eq!(gamestate.asteroids.len(), reference.asteroids.len());
+            for (a, b) in gamestate.asteroids.iter().zip(&reference.asteroids) {
+                assert_eq!(a.position, b.position);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fire_once_per_press() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.game_started = true;
+        gamestate.input.fire = true;
+
+        // A long frame runs several ticks but fires a single missile
+        gamestate.update(TICKS * 3.0);
+        assert_eq!(gamestate.loop_number, 3);
+        assert_eq!(gamestate.missiles.len(), 1);
+    }
+
+    #[test]
+    fn test_auto_fire_is_limited() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.game_started = true;
+
+        // Fire held on every tick, like the debug auto-fire
+        let mut fired = 0;
+        for _ in 0..120 {
+            gamestate.input.fire = true;
+            let before = gamestate.missiles.len();
+            gamestate.update(TICKS);
+            fired += gamestate.missiles.len().saturating_sub(before);
+            assert!(gamestate.missiles.len() <= gamestate.spaceship.weapon.max_missiles);
+        }
+        assert!(fired > 1);
+        assert!(fired < 20);
+    }
+
+    #[test]
+    fn test_hyperspace_jump() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.new_game();
+        gamestate.hyperspace_config.failure_chance = 0.0;
+        // Nothing for the missiles to hit
+        gamestate.asteroids.clear();
+        let start = gamestate.spaceship.position;
+
+        // Jump then try to fire at once: the spaceship is re-materializing
+        gamestate.input.hyperspace = true;
+        gamestate.update(TICKS);
+        gamestate.input.fire = true;
+        gamestate.update(TICKS);
+        assert_ne!(gamestate.spaceship.position, start);
+        assert!(gamestate.world.contains(gamestate.spaceship.position));
+        assert!(gamestate.missiles.is_empty());
+
+        // No second jump during the cooldown
+        let position = gamestate.spaceship.position;
+        gamestate.input.hyperspace = true;
+        gamestate.update(TICKS);
+        assert_eq!(gamestate.spaceship.position, position);
+
+        // Fire works again once materialized
+        gamestate.update(gamestate.hyperspace_config.materialization as f64);
+        gamestate.input.fire = true;
+        gamestate.update(TICKS);
+        assert_eq!(gamestate.missiles.len(), 1);
+    }
+
+    #[test]
+    fn test_hyperspace_failure() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.new_game();
+        gamestate.hyperspace_config.failure_chance = 1.0;
+
+        gamestate.input.hyperspace = true;
+        gamestate.update(TICKS);
+        assert_eq!(gamestate.lives, 2);
+        assert_eq!(gamestate.spaceship.position, gamestate.world.center());
+    }
+
+    #[test]
+    fn test_hyperspace_is_deterministic() {
+        let jump = |seed| {
+            let mut gamestate = Gamestate::new(World::new(800.0, 600.0), seed);
+            gamestate.new_game();
+            gamestate.hyperspace_config.failure_chance = 0.0;
+            gamestate.input.hyperspace = true;
+            gamestate.update(TICKS);
+            gamestate.spaceship.position
+        };
+        assert_eq!(jump(5), jump(5));
+        assert_ne!(jump(5), jump(6));
+    }
+
+    #[test]
+    fn test_ufo_comes_and_shoots() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 4);
+        gamestate.new_game();
+        gamestate.asteroids.clear();
+        gamestate
+            .asteroids
+            .push(still_asteroid(vec2(700.0, 500.0), 1));
+        gamestate.ufo_timer = TICKS as f32;
+
+        gamestate.update(TICKS);
+        assert_eq!(gamestate.ufos.len(), 1);
+        // The countdown to the next one waits for this one to leave
+        gamestate.update(TICKS);
+        assert_eq!(gamestate.ufo_timer, gamestate.ufo_config.spawn_interval);
+        gamestate.ufo_timer = 0.0;
+        // One saucer at a time, and it fires once reloaded
+        for _ in 0..80 {
+            gamestate.update(TICKS);
+        }
+        assert!(gamestate.ufos.len() <= 1);
+        assert!(!gamestate.ufo_missiles.is_empty());
+    }
+
+    #[test]
+    fn test_ufo_holds_fire_while_respawning() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.new_game();
+        gamestate.asteroids.clear();
+        // The saucer keeps the center crowded, and has reloaded
+        let center = gamestate.world.center();
+        gamestate.ufos.push(Ufo::new(
+            UfoKind::Small,
+            center + vec2(60.0, 0.0),
+            1.0,
+            &gamestate.ufo_config,
+        ));
+        gamestate.ufos[0].reload = 0.0;
+        gamestate.destroy_spaceship();
+
+        gamestate.update(TICKS);
+        assert!(gamestate.is_respawning());
+        assert!(gamestate.ufo_missiles.is_empty());
+    }
+
+    #[test]
+    fn test_ufo_shot_down() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.game_started = true;
+        gamestate.ufos.push(Ufo::new(
+            UfoKind::Small,
+            vec2(100.0, 100.0),
+            1.0,
+            &gamestate.ufo_config,
+        ));
+        gamestate.missiles.push(Missile::new(
+            vec2(100.0, 100.0),
+            Vec2::ZERO,
+            0.0,
+            &MissileConfig::default(),
+        ));
+
+        gamestate.update(TICKS);
+        assert_eq!(gamestate.score, UfoKind::Small.score());
+        assert!(gamestate.ufos.iter().all(|ufo| !ufo.active));
+    }
+
+    #[test]
+    fn test_ufo_collisions_across_edges() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.game_started = true;
+        gamestate.spaceship.position = vec2(795.0, 300.0);
+        gamestate.spaceship.invulnerability = 0.0;
+        let config = gamestate.ufo_config;
+        // Une soucoupe abattue par le bord, une autre percutée par le vaisseau
+        gamestate
+            .ufos
+            .push(Ufo::new(UfoKind::Small, vec2(798.0, 100.0), 1.0, &config));
+        gamestate
+            .ufos
+            .push(Ufo::new(UfoKind::Large, vec2(5.0, 300.0), 1.0, &config));
+        gamestate.missiles.push(Missile::new(
+            vec2(2.0, 100.0),
+            Vec2::ZERO,
+            0.0,
+            &MissileConfig::default(),
+        ));
+
+        check_collision_ufo(&mut gamestate);
+        assert!(gamestate.ufos.iter().all(|ufo| !ufo.active));
+        assert!(gamestate.missiles.iter().all(|missile| !missile.active));
+        assert_eq!(
+            gamestate.score,
+            UfoKind::Small.score() + UfoKind::Large.score()
+        );
+        assert!(gamestate.spaceship.shield < gamestate.shield_config.max_shield);
+    }
+
+    #[test]
+    fn test_ufo_missile_hits_spaceship() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.game_started = true;
+        let config = gamestate.ufo_config.missile;
+        gamestate.ufo_missiles.push(Missile::new(
+            gamestate.spaceship.position,
+            Vec2::ZERO,
+            0.0,
+            &config,
+        ));
+
+        gamestate.update(TICKS);
+        assert!(gamestate.spaceship.shield < gamestate.shield_config.max_shield);
+        assert!(gamestate.ufo_missiles.iter().all(|missile| !missile.active));
+    }
+
+    #[test]
+    fn test_ufo_missile_damages_solid_asteroid() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.game_started = true;
+        let mut asteroid = still_asteroid(vec2(100.0, 100.0), 1);
+        asteroid.hit_points = 3;
+        gamestate.asteroids.push(asteroid);
+        let config = gamestate.ufo_config.missile;
+
+        // Chaque missile coûte un point de vie, le troisième le brise
+        for expected_hit_points in [2, 1, 0] {
+            gamestate
+                .ufo_missiles
+                .push(Missile::new(vec2(100.0, 100.0), Vec2::ZERO, 0.0, &config));
+            check_collision_ufo(&mut gamestate);
+            assert!(gamestate.ufo_missiles.iter().all(|missile| !missile.active));
+            if expected_hit_points > 0 {
+                assert_eq!(gamestate.asteroids.len(), 1);
+                assert_eq!(gamestate.asteroids[0].hit_points, expected_hit_points);
+            }
+        }
+        assert!(gamestate.asteroids.is_empty());
+        // The saucers never score for the player
+        assert_eq!(gamestate.score, 0);
+    }
+
+    #[test]
+    fn test_paused_simulation() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.new_game();
+        gamestate.simulation_speed = 0.0;
+        gamestate.update(1.0);
+        assert_eq!(gamestate.loop_number, 0);
+    }
+
+    /// A still asteroid of size 1 on top of the spaceship.
+    fn asteroid_on_spaceship(gamestate: &Gamestate) -> Asteroid {
+        still_asteroid(gamestate.spaceship.position, 1)
+    }
+
+    #[test]
+    fn test_hit_lowers_shield_before_lives() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.game_started = true;
+        gamestate.asteroids.push(asteroid_on_spaceship(&gamestate));
+        gamestate.asteroids.push(asteroid_on_spaceship(&gamestate));
+
+        gamestate.update(TICKS);
+        assert_eq!(gamestate.lives, 3);
+        assert_eq!(gamestate.spaceship.shield, 80.0);
+        // The second asteroid cannot hit during the invulnerability
+        assert_eq!(gamestate.asteroids.len(), 1);
+        gamestate.update(TICKS);
+        assert_eq!(gamestate.asteroids.len(), 1);
+
+        // Without shield, the next hit costs a life
+        gamestate.spaceship.shield = 0.0;
+        gamestate.spaceship.invulnerability = 0.0;
+        gamestate.update(TICKS);
+        assert_eq!(gamestate.lives, 2);
+        assert_eq!(
+            gamestate.spaceship.shield,
+            gamestate.shield_config.max_shield
+        );
+        assert!(gamestate.spaceship.is_invulnerable());
+    }
+
+    #[test]
+    fn test_nearest_clear_spot_across_edge() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        // Une rangée d'astéroïdes ferme le passage vers la gauche
+        for y in (0..600).step_by(60) {
+            gamestate
+                .asteroids
+                .push(still_asteroid(vec2(680.0, y as f32), 1));
+        }
+        let position = vec2(795.0, 300.0);
+        let spot = gamestate.nearest_clear_spot(position).unwrap();
+        // Quelques pixels à droite, par le bord, plutôt que tout l'écran
+        assert!(spot.x < 400.0, "{spot}");
+        assert!(gamestate.world.distance(spot, position) < 150.0);
+        assert!(gamestate.is_clear(spot));
+    }
+
+    #[test]
+    fn test_respawn_waits_for_clear_center() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.game_started = true;
+        let center = gamestate.world.center();
+        // A still asteroid sits where the spaceship should come back
+        gamestate
+            .asteroids
+            .push(still_asteroid(center + vec2(50.0, 0.0), 3));
+        gamestate.asteroids[0].turn_rate = 0.0;
+        gamestate.destroy_spaceship();
+
+        gamestate.update(TICKS);
+        assert!(gamestate.is_respawning());
+        assert_eq!(gamestate.lives, 2);
+
+        // Too long: the spaceship comes back at the nearest clear place
+        gamestate.update(gamestate.respawn_config.max_wait as f64);
+        assert!(!gamestate.is_respawning());
+        let position = gamestate.spaceship.position;
+        assert_ne!(position, center);
+        assert!(gamestate.is_clear(position));
+        assert!((position - center).length() < 400.0);
+        assert!(gamestate.spaceship.is_invulnerable());
+    }
+
+    #[test]
+    fn test_wave_keeps_away_from_player() {
+        for seed in 0..20 {
+            let mut gamestate = Gamestate::new(World::new(800.0, 600.0), seed);
+            gamestate.new_game();
+            let min_distance = gamestate.level_config.min_spawn_distance;
+            for asteroid in &gamestate.asteroids {
+                assert!(
+                    (asteroid.position - gamestate.spaceship.position).length() >= min_distance
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_extra_lives() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.new_game();
+        gamestate.asteroids.clear();
+        gamestate.update(TICKS);
+        assert_eq!(gamestate.lives, 3);
+
+        // Two milestones at once give two lives
+        gamestate.score = 20_000;
+        gamestate.update(TICKS);
+        assert_eq!(gamestate.lives, 5);
+        assert!(gamestate.extra_life_notice > 0.0);
+        assert_eq!(gamestate.next_extra_life, 30_000);
+
+        // Never more than the maximum
+        gamestate.score = 30_000;
+        gamestate.update(TICKS);
+        assert_eq!(gamestate.lives, gamestate.lives_config.max_lives);
+        assert_eq!(gamestate.next_extra_life, 40_000);
+
+        gamestate.update(gamestate.lives_config.notice_duration as f64);
+        assert_eq!(gamestate.extra_life_notice, 0.0);
+    }
+
+    #[test]
+    fn test_missile_destroys_asteroid() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.game_started = true;
+        gamestate
+            .asteroids
+            .push(still_asteroid(vec2(100.0, 100.0), 1));
+        gamestate.missiles.push(Missile::new(
+            vec2(100.0, 100.0),
+            Vec2::ZERO,
+            0.0,
+            &MissileConfig::default(),
+        ));
+
+        gamestate.level_config.campaign = Some(1);
+        gamestate.update(TICKS);
+
+        assert_eq!(gamestate.score, 300);
+        assert!(gamestate.missiles.is_empty());
+        assert!(gamestate.game_won);
+    }
+
+    #[test]
+    fn test_fast_missile_does_not_tunnel() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.game_started = true;
+        gamestate.simulation_speed = 10.0;
+        // 100 px per tick, the missile is never within reach of the
+        // asteroid at the end of a tick
+        gamestate.missile_config.speed = 6000.0;
+        gamestate
+            .asteroids
+            .push(still_asteroid(vec2(555.0, 300.0), 1));
+        gamestate.input.fire = true;
+
+        gamestate.update(TICKS);
+
+        assert_eq!(gamestate.loop_number, 10);
+        assert_eq!(gamestate.score, 300);
+        assert!(gamestate.missiles.is_empty());
+    }
+
+    #[test]
+    fn test_particles_follow_the_simulation() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.game_started = true;
+        gamestate
+            .asteroids
+            .push(still_asteroid(vec2(100.0, 100.0), 2));
+        gamestate.missiles.push(Missile::new(
+            vec2(100.0, 100.0),
+            Vec2::ZERO,
+            0.0,
+            &MissileConfig::default(),
+        ));
+        gamestate.update(TICKS);
+        let debris = gamestate.particles.live().len();
+        assert_eq!(debris, 2 * gamestate.particles.config.debris_per_size);
+
+        // Figées pendant la pause
+        let positions: Vec<Vec2> = gamestate
+            .particles
+            .live()
+            .iter()
+            .map(|p| p.position)
+            .collect();
+        gamestate.simulation_speed = 0.0;
+        gamestate.update(1.0);
+        assert_eq!(
+            gamestate
+                .particles
+                .live()
+                .iter()
+                .map(|p| p.position)
+                .collect::<Vec<Vec2>>(),
+            positions
+        );
+
+        // The exhaust only comes with the thrust
+        gamestate.simulation_speed = 1.0;
+        gamestate.asteroids.clear();
+        gamestate.particles.clear();
+        gamestate.input.thrust = true;
+        gamestate.update(TICKS);
+        assert_eq!(
+            gamestate.particles.live().len(),
+            gamestate.particles.config.exhaust
+        );
+    }
+
+    #[test]
+    fn test_class_hit_points_and_score() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.game_started = true;
+        gamestate.asteroid_classes = AsteroidClasses::parse(
+            "class 3 * 1 0 0 0 0 1 0\nclass 2 * 1 0 0 0 0 1 0\nclass 1 * 50 0 0 0 0 2 0\n",
+        )
+        .unwrap();
+        let mut asteroid = still_asteroid(vec2(100.0, 100.0), 1);
+        asteroid.hit_points = 2;
+        gamestate.asteroids.push(asteroid);
+
+        // Le premier missile ne fait qu'endommager l'astéroïde
+        for expected_hit_points in [1, 0] {
+            gamestate.missiles.push(Missile::new(
+                vec2(100.0, 100.0),
+                Vec2::ZERO,
+                0.0,
+                &MissileConfig::default(),
+            ));
+            check_collision_asteroid_missile(&mut gamestate);
+            assert!(gamestate.missiles.is_empty());
+            if expected_hit_points > 0 {
+                assert_eq!(gamestate.asteroids[0].hit_points, expected_hit_points);
+                assert_eq!(gamestate.score, 0);
+            }
+        }
+        assert!(gamestate.asteroids.is_empty());
+        assert_eq!(gamestate.score, 50);
+    }
+
+    #[test]
+    fn test_collisions_across_edges() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.game_started = true;
+        gamestate.spaceship.position = vec2(795.0, 300.0);
+        gamestate.spaceship.invulnerability = 0.0;
+        // Un astéroïde à cheval sur le bord gauche
+        gamestate
+            .asteroids
+            .push(still_asteroid(vec2(10.0, 300.0), 1));
+        // Et un autre en bas, touché par un missile en haut
+        gamestate
+            .asteroids
+            .push(still_asteroid(vec2(400.0, 595.0), 1));
+        gamestate.missiles.push(Missile::new(
+            vec2(400.0, 5.0),
+            Vec2::ZERO,
+            0.0,
+            &MissileConfig::default(),
+        ));
+        let shield = gamestate.spaceship.shield;
+
+        gamestate.update(TICKS);
+
+        assert!(gamestate.spaceship.shield < shield);
+        assert!(gamestate.asteroids.is_empty());
+        assert_eq!(gamestate.score, 300);
+    }
+
+    #[test]
+    fn test_outline_collisions() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        let mut asteroid = still_asteroid(vec2(100.0, 100.0), 2);
+        // Un losange : les coins du cercle englobant sont vides
+        asteroid.shape = vec![
+            vec2(1.0, 0.0),
+            vec2(0.0, 1.0),
+            vec2(-1.0, 0.0),
+            vec2(0.0, -1.0),
+        ];
+        gamestate.asteroids.push(asteroid);
+
+        let missile = |position: Vec2| Missile::new(position, Vec2::ZERO, 0.0, &Default::default());
+        gamestate.missiles.push(missile(vec2(128.0, 128.0)));
+        check_collision_asteroid_missile(&mut gamestate);
+        assert_eq!(gamestate.asteroids.len(), 1);
+
+        gamestate.missiles.push(missile(vec2(110.0, 110.0)));
+        check_collision_asteroid_missile(&mut gamestate);
+        // The fragments are cut from the outline, around where they were
+        assert_eq!(gamestate.asteroids.len(), 2);
+        for fragment in &gamestate.asteroids {
+            assert!(!fragment.shape.is_empty());
+            assert!((fragment.position - vec2(100.0, 100.0)).length() < 40.0);
+        }
+    }
+
+    #[test]
+    fn test_explosive_asteroid_breaks_neighbours() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        let asteroid = |x: f32, texture: &str| {
+            let mut asteroid = still_asteroid(vec2(x, 100.0), 1);
+            asteroid.texture = texture.to_string();
+            asteroid
+        };
+        gamestate.asteroids.push(asteroid(100.0, "asteroid_1.png"));
+        gamestate.asteroids.push(asteroid(150.0, "asteroid_0.png"));
+        gamestate.asteroids.push(asteroid(500.0, "asteroid_0.png"));
+        gamestate.missiles.push(Missile::new(
+            vec2(100.0, 100.0),
+            Vec2::ZERO,
+            0.0,
+            &MissileConfig::default(),
+        ));
+
+        check_collision_asteroid_missile(&mut gamestate);
+
+        // Le voisin est pris dans l'explosion, pas l'astéroïde éloigné
+        assert_eq!(gamestate.asteroids.len(), 1);
+        assert_eq!(gamestate.asteroids[0].position.x, 500.0);
+        assert_eq!(gamestate.score, 600);
+    }
+
+    #[test]
+    fn test_bouncing_conserves_momentum() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 5);
+        gamestate.physics_config.enabled = true;
+        gamestate.physics_config.split_energy = None;
+        // Ni vaisseau ni soucoupe pour casser des astéroïdes
+        gamestate.spaceship.invulnerability = f32::MAX;
+        gamestate.ufo_timer = f32::MAX;
+        for _ in 0..20 {
+            let asteroid = Asteroid::new_random(
+                &gamestate.world,
+                &mut gamestate.rng,
+                &gamestate.asteroid_classes,
+            );
+            gamestate.asteroids.push(asteroid);
+        }
+        let momentum = |asteroids: &[Asteroid]| -> Vec2 {
+            asteroids.iter().map(|a| a.velocity * a.mass()).sum()
+        };
+        let energy = |asteroids: &[Asteroid]| -> f32 {
+            asteroids
+                .iter()
+                .map(|a| 0.5 * a.mass() * a.velocity.length_squared())
+                .sum()
+        };
+        let (before, kinetic) = (momentum(&gamestate.asteroids), energy(&gamestate.asteroids));
+        let velocities: Vec<Vec2> = gamestate.asteroids.iter().map(|a| a.velocity).collect();
+
+        for _ in 0..1200 {
+            gamestate.update(TICKS);
+        }
+
+        assert_eq!(gamestate.asteroids.len(), 20);
+        // Some of them did collide
+        assert!(gamestate
+            .asteroids
+            .iter()
+            .zip(&velocities)
+            .any(|(asteroid, velocity)| asteroid.velocity != *velocity));
+        let scale = kinetic.sqrt()
+            * gamestate
+                .asteroids
+                .iter()
+                .map(|a| a.mass())
+                .sum::<f32>()
+                .sqrt();
+        assert!((momentum(&gamestate.asteroids) - before).length() < scale * 1e-4);
+        assert!((energy(&gamestate.asteroids) - kinetic).abs() < kinetic * 1e-4);
+    }
+
+    #[test]
+    fn test_hard_impact_splits_asteroids() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.physics_config.enabled = true;
+        let asteroid = |x: f32, speed: f32| {
+            let mut asteroid = still_asteroid(vec2(x, 300.0), 3);
+            asteroid.velocity = vec2(speed, 0.0);
+            asteroid
+        };
+
+        // Un choc lent, ils rebondissent seulement
+        gamestate.asteroids = vec![asteroid(100.0, 5.0), asteroid(215.0, -5.0)];
+        check_collision_asteroids(&mut gamestate);
+        assert_eq!(gamestate.asteroids.len(), 2);
+        assert!((gamestate.asteroids[0].velocity - vec2(-5.0, 0.0)).length() < 1e-3);
+
+        // Un choc violent les casse tous les deux, sans points
+        gamestate.asteroids = vec![asteroid(100.0, 200.0), asteroid(215.0, -200.0)];
+        check_collision_asteroids(&mut gamestate);
+        assert_eq!(gamestate.asteroids.len(), 4);
+        assert!(gamestate
+            .asteroids
+            .iter()
+            .all(|asteroid| asteroid.size == 2));
+        assert_eq!(gamestate.score, 0);
+    }
+
+    #[test]
+    fn test_cleared_wave_starts_next_level() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        gamestate.new_game();
+        gamestate.asteroids.clear();
+
+        // The game is not won, the next level comes after the intermission
+        gamestate.update(TICKS);
+        assert!(gamestate.game_started);
+        assert!(!gamestate.game_won);
+        assert_eq!(gamestate.level, 2);
+        assert!(gamestate.asteroids.is_empty());
+        assert!(gamestate.intermission > 0.0);
+
+        gamestate.update(gamestate.level_config.intermission as f64 + TICKS);
+        assert_eq!(gamestate.intermission, 0.0);
+        let wave = gamestate.level_config.wave(2);
+        assert_eq!(gamestate.asteroids.len(), wave.count);
+        assert!(wave.count > gamestate.level_config.wave(1).count);
+    }
+}
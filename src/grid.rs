@@ -0,0 +1,154 @@
+use macroquad::math::Vec2;
+
+use crate::asteroid::Asteroid;
+use crate::world::World;
+
+/// Side of a cell, a bit more than the largest asteroid.
+pub const CELL_SIZE: f32 = 128.0;
+
+/// A uniform grid over the playfield, to find the objects near a position
+/// without testing all of them.
+///
+/// The cells wrap around like the playfield, so an object crossing an edge
+/// is found from both sides. The grid only gives candidates, which still
+/// have to be tested, and must be rebuilt once the objects have moved.
+#[derive(Clone, Debug)]
+pub struct Grid {
+    cell_size: f32,
+    world: World,
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self::new(CELL_SIZE)
+    }
+}
+
+impl Grid {
+    /// Create an empty grid with square cells of the given side.
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            world: World::new(0.0, 0.0),
+            columns: 1,
+            rows: 1,
+            cells: vec![Vec::new()],
+        }
+    }
+
+    /// Empties the grid and fits it to the playfield. The cells keep their
+    /// memory from one tick to the next.
+    pub fn clear(&mut self, world: &World) {
+        self.world = *world;
+        self.columns = ((world.width / self.cell_size).ceil() as usize).max(1);
+        self.rows = ((world.height / self.cell_size).ceil() as usize).max(1);
+        self.cells.resize_with(self.columns * self.rows, Vec::new);
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+    }
+
+    /// The playfield covered by the grid.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Adds the object `index`, a circle, to every cell it overlaps.
+    pub fn insert(&mut self, index: usize, position: Vec2, radius: f32) {
+        for row in self.span(position.y, radius, self.world.height, self.rows) {
+            for column in self.span(position.x, radius, self.world.width, self.columns) {
+                self.cells[row * self.columns + column].push(index);
+            }
+        }
+    }
+
+    /// Fills the grid with the asteroids still in the game, by their index.
+    pub fn rebuild(&mut self, world: &World, asteroids: &[Asteroid]) {
+        self.clear(world);
+        for (index, asteroid) in asteroids.iter().enumerate() {
+            if asteroid.active {
+                self.insert(index, asteroid.position, asteroid.radius());
+            }
+        }
+    }
+
+    /// The objects which may touch a circle, each once and in the order of
+    /// their index.
+    pub fn query(&self, position: Vec2, radius: f32) -> Vec<usize> {
+        let mut found = Vec::new();
+        for row in self.span(position.y, radius, self.world.height, self.rows) {
+            for column in self.span(position.x, radius, self.world.width, self.columns) {
+                found.extend_from_slice(&self.cells[row * self.columns + column]);
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    // The cells along one axis covered from `center - radius` to
+    // `center + radius`, going through the edge if needed
+    fn span(&self, center: f32, radius: f32, size: f32, count: usize) -> Vec<usize> {
+        if size <= 0.0 || 2.0 * radius >= size {
+            return (0..count).collect();
+        }
+        let cell = |coord: f32| ((coord.rem_euclid(size) / self.cell_size) as usize).min(count - 1);
+        let (first, last) = (cell(center - radius), cell(center + radius));
+        if first <= last {
+            (first..=last).collect()
+        } else {
+            (first..count).chain(0..=last).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::rand::rngs::SmallRng;
+    use ::rand::{Rng, SeedableRng};
+    use macroquad::math::vec2;
+
+    #[test]
+    fn test_query_wraps_around() {
+        let mut grid = Grid::default();
+        grid.clear(&World::new(800.0, 600.0));
+        grid.insert(0, vec2(790.0, 300.0), 20.0);
+        grid.insert(1, vec2(400.0, 300.0), 20.0);
+
+        // Vu de l'autre côté du bord
+        assert_eq!(grid.query(vec2(5.0, 300.0), 10.0), vec![0]);
+        assert_eq!(grid.query(vec2(400.0, 590.0), 10.0), Vec::<usize>::new());
+        assert_eq!(grid.query(vec2(400.0, 300.0), 500.0), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_query_finds_every_overlap() {
+        let world = World::new(1000.0, 700.0);
+        let mut rng = SmallRng::seed_from_u64(3);
+        let circles: Vec<(Vec2, f32)> = (0..300)
+            .map(|_| {
+                let position = vec2(rng.gen_range(0.0..1000.0), rng.gen_range(0.0..700.0));
+                (position, rng.gen_range(1.0..60.0))
+            })
+            .collect();
+        let mut grid = Grid::default();
+        grid.clear(&world);
+        for (index, (position, radius)) in circles.iter().enumerate() {
+            grid.insert(index, *position, *radius);
+        }
+
+        // Aucune paire en contact n'est oubliée, même à travers les bords
+        for (position, radius) in &circles {
+            let found = grid.query(*position, *radius);
+            for (index, (other, other_radius)) in circles.iter().enumerate() {
+                if world.distance(*position, *other) < radius + other_radius {
+                    assert!(found.contains(&index));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use chrono::{DateTime, Local};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// First line of the file, followed by the version of the format
+const HEADER: &str = "asteroids-highscores";
+const VERSION: u32 = 1;
+
+/// One line of the table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u128,
+    pub date: DateTime<Local>,
+    pub seed: u64,
+}
+
+impl HighScore {
+    pub const MAX_NAME_LENGTH: usize = 12;
+
+    /// Create an entry dated now. The name is cleaned to fit in the table.
+    pub fn new(name: &str, score: u128, seed: u64) -> Self {
+        Self {
+            name: Self::clean_name(name),
+            score,
+            date: Local::now(),
+            seed,
+        }
+    }
+
+    /// Keeps the printable characters of a name, up to the maximum length.
+    pub fn clean_name(name: &str) -> String {
+        let name: String = name
+            .trim()
+            .chars()
+            .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.'))
+            .take(Self::MAX_NAME_LENGTH)
+            .collect();
+        let name = name.trim();
+        if name.is_empty() {
+            "Anonymous".to_string()
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+/// The best scores, from the highest to the lowest.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HighScores {
+    pub entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    /// How many scores the table keeps.
+    pub const CAPACITY: usize = 10;
+
+    /// Returns whether a score would enter the table.
+    pub fn qualifies(&self, score: u128) -> bool {
+        score > 0
+            && (self.entries.len() < Self::CAPACITY
+                || self.entries.last().is_some_and(|last| score > last.score))
+    }
+
+    /// Adds an entry and returns its rank from 0, or `None` if it did not
+    /// make it into the table. Ties go to the oldest score.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        if !self.qualifies(entry.score) {
+            return None;
+        }
+        let rank = self
+            .entries
+            .iter()
+            .position(|other| entry.score > other.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(rank, entry);
+        self.entries.truncate(Self::CAPACITY);
+        Some(rank)
+    }
+
+    /// Encodes the table, one tab separated entry per line.
+    pub fn encode(&self) -> String {
+        let mut lines = vec![format!("{HEADER} {VERSION}")];
+        for entry in &self.entries {
+            // The name comes last as it may contain spaces
+            lines.push(format!(
+                "{}\t{}\t{}\t{}",
+                entry.score,
+                entry.seed,
+                entry.date.to_rfc3339(),
+                entry.name
+            ));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// Decodes a table, skipping the entries that cannot be read.
+    /// Returns the table and the number of damaged lines.
+    pub fn decode(text: &str) -> (Self, usize) {
+        let mut lines = text.lines();
+        if lines.next().map(str::trim) != Some(&format!("{HEADER} {VERSION}")) {
+            return (Self::default(), text.lines().count().max(1));
+        }
+
+        let mut table = Self::default();
+        let mut damaged = 0;
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            match Self::decode_entry(line) {
+                Some(entry) => {
+                    table.insert(entry);
+                }
+                None => damaged += 1,
+            }
+        }
+        (table, damaged)
+    }
+
+    fn decode_entry(line: &str) -> Option<HighScore> {
+        let mut fields = line.splitn(4, '\t');
+        let score = fields.next()?.parse().ok()?;
+        let seed = fields.next()?.parse().ok()?;
+        let date = DateTime::parse_from_rfc3339(fields.next()?).ok()?;
+        let name = fields.next()?;
+        Some(HighScore {
+            name: HighScore::clean_name(name),
+            score,
+            date: date.with_timezone(&Local),
+            seed,
+        })
+    }
+
+    /// Reads the table from a file. A missing file gives an empty table.
+    ///
+    /// A damaged file never stops the game: the entries that can be read
+    /// are kept, the file is copied aside with a `.bak` extension and a
+    /// warning is returned.
+    pub fn load(path: &Path) -> (Self, Option<String>) {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return (Self::default(), None)
+            }
+            Err(error) => {
+                return (
+                    Self::default(),
+                    Some(format!("Cannot read the high scores: {error}")),
+                )
+            }
+        };
+
+        let (table, damaged) = Self::decode(&text);
+        if damaged == 0 {
+            return (table, None);
+        }
+        let backup = PathBuf::from(format!("{}.bak", path.display()));
+        let kept = match fs::write(&backup, &text) {
+            Ok(()) => format!("a copy was kept in {}", backup.display()),
+            Err(error) => format!("no copy could be kept: {error}"),
+        };
+        (
+            table,
+            Some(format!(
+                "The high scores were damaged, {damaged} line(s) lost, {kept}"
+            )),
+        )
+    }
+
+    /// Writes the table to a file.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.encode())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table_of(scores: &[u128]) -> HighScores {
+        let mut table = HighScores::default();
+        for (index, score) in scores.iter().enumerate() {
+            table.insert(HighScore::new(&format!("P{index}"), *score, index as u64));
+        }
+        table
+    }
+
+    #[test]
+    fn test_insert_keeps_order_and_capacity() {
+        let mut table = table_of(&[500, 100, 300, 300, 200, 900, 700, 800, 600, 400]);
+        assert_eq!(table.entries.len(), HighScores::CAPACITY);
+        assert_eq!(table.entries[0].score, 900);
+        assert_eq!(table.entries[9].score, 100);
+        // The first 300 stays ahead of the second one
+        assert_eq!(table.entries[6].name, "P2");
+
+        assert!(!table.qualifies(100));
+        assert_eq!(table.insert(HighScore::new("Low", 50, 0)), None);
+        assert_eq!(table.insert(HighScore::new("New", 850, 0)), Some(1));
+        assert_eq!(table.entries.len(), HighScores::CAPACITY);
+        assert_eq!(table.entries[9].score, 200);
+    }
+
+    #[test]
+    fn test_zero_never_qualifies() {
+        assert!(!HighScores::default().qualifies(0));
+    }
+
+    #[test]
+    fn test_clean_name() {
+        assert_eq!(HighScore::clean_name("  Ada\tLovelace\n"), "AdaLovelace");
+        assert_eq!(HighScore::clean_name("\t"), "Anonymous");
+        assert_eq!(HighScore::clean_name("abcdefghijklmnop").len(), 12);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let table = table_of(&[300, 100, 200]);
+        let (decoded, damaged) = HighScores::decode(&table.encode());
+        assert_eq!(damaged, 0);
+        assert_eq!(decoded.entries.len(), 3);
+        for (a, b) in decoded.entries.iter().zip(&table.entries) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.score, b.score);
+            assert_eq!(a.seed, b.seed);
+            assert_eq!(a.date.timestamp(), b.date.timestamp());
+        }
+    }
+
+    #[test]
+    fn test_damaged_file_recovery() {
+        let mut text = table_of(&[300, 100]).encode();
+        text.push_str("garbage\n12\tnot a seed\t2024\tName\n");
+        let (table, damaged) = HighScores::decode(&text);
+        assert_eq!(damaged, 2);
+        assert_eq!(table.entries.len(), 2);
+
+        let (table, damaged) = HighScores::decode("\u{0}\u{1}binary");
+        assert_eq!(damaged, 1);
+        assert!(table.entries.is_empty());
+    }
+
+    #[test]
+    fn test_load_damaged_file() {
+        let path = std::env::temp_dir().join("asteroids_test_highscores.txt");
+        fs::write(&path, "not a table").unwrap();
+
+        let (table, warning) = HighScores::load(&path);
+        assert!(table.entries.is_empty());
+        assert!(warning.is_some());
+
+        let backup = PathBuf::from(format!("{}.bak", path.display()));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "not a table");
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&backup).unwrap();
+
+        // A missing file is simply an empty table
+        assert_eq!(HighScores::load(&path), (HighScores::default(), None));
+    }
+}
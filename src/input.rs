@@ -0,0 +1,49 @@
+/// The commands of the player for one tick of the simulation.
+///
+/// The speed modifier does not change what a tick does, only how many ticks
+/// run per frame; it is kept so a replay can be watched at the recorded pace.
+///
+/// The held keys are sampled every frame while the one-shot actions (`fire`,
+/// `stop`, `hyperspace`) stay set until a tick consumes them, so a press is
+/// never lost when a frame runs no tick and never repeated when a frame runs
+/// several.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Input {
+    pub thrust: bool,
+    pub reverse: bool,
+    pub rotate_left: bool,
+    pub rotate_right: bool,
+    pub fire: bool,
+    pub stop: bool,
+    pub hyperspace: bool,
+    pub speed: SpeedModifier,
+}
+
+/// The speed modifiers the player can hold during a game.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpeedModifier {
+    #[default]
+    Normal,
+    Slow,
+    Fast,
+}
+
+impl SpeedModifier {
+    /// The factor applied to the time of the simulation.
+    pub fn factor(&self) -> f64 {
+        match self {
+            SpeedModifier::Normal => 1.0,
+            SpeedModifier::Slow => 0.1,
+            SpeedModifier::Fast => 10.0,
+        }
+    }
+}
+
+impl Input {
+    /// Clears the one-shot actions once a tick has applied them.
+    pub fn consume(&mut self) {
+        self.fire = false;
+        self.stop = false;
+        self.hyperspace = false;
+    }
+}
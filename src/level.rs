@@ -0,0 +1,90 @@
+/// The asteroids of one wave.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Wave {
+    pub count: usize,
+    /// Factor applied to the speed of every asteroid of the wave.
+    pub speed_factor: f32,
+}
+
+/// How the waves get harder from one level to the next.
+///
+/// The game goes on endlessly unless `campaign` gives a last level, in
+/// which case clearing it wins the game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelConfig {
+    /// Asteroids of the first level, and added by each following level.
+    pub first_count: usize,
+    pub count_per_level: usize,
+    pub max_count: usize,
+    /// Speed added by each level, as a fraction of the initial speed.
+    pub speed_per_level: f32,
+    pub max_speed_factor: f32,
+    /// Seconds of calm between two waves.
+    pub intermission: f32,
+    /// Distance between the player and the asteroids of a new wave.
+    pub min_spawn_distance: f32,
+    pub campaign: Option<u32>,
+}
+
+impl Default for LevelConfig {
+    fn default() -> Self {
+        Self {
+            first_count: 6,
+            count_per_level: 2,
+            max_count: 20,
+            speed_per_level: 0.1,
+            max_speed_factor: 2.0,
+            intermission: 3.0,
+            min_spawn_distance: 200.0,
+            campaign: None,
+        }
+    }
+}
+
+impl LevelConfig {
+    /// The wave of a level, counted from 1.
+    pub fn wave(&self, level: u32) -> Wave {
+        let steps = level.saturating_sub(1);
+        Wave {
+            count: (self.first_count + self.count_per_level * steps as usize).min(self.max_count),
+            speed_factor: (1.0 + self.speed_per_level * steps as f32).min(self.max_speed_factor),
+        }
+    }
+
+    /// Returns whether clearing `level` ends the campaign.
+    pub fn is_last(&self, level: u32) -> bool {
+        self.campaign.is_some_and(|last| level >= last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waves_escalate() {
+        let config = LevelConfig::default();
+        assert_eq!(
+            config.wave(1),
+            Wave {
+                count: 6,
+                speed_factor: 1.0
+            }
+        );
+        assert_eq!(config.wave(3).count, 10);
+        assert!(config.wave(3).speed_factor > config.wave(2).speed_factor);
+
+        // Plafonné au-delà d'un certain niveau
+        assert_eq!(config.wave(100).count, config.max_count);
+        assert_eq!(config.wave(100).speed_factor, config.max_speed_factor);
+    }
+
+    #[test]
+    fn test_campaign() {
+        let mut config = LevelConfig::default();
+        assert!(!config.is_last(1000));
+        config.campaign = Some(5);
+        assert!(!config.is_last(4));
+        assert!(config.is_last(5));
+    }
+}
@@ -0,0 +1,66 @@
+//! The model of the Asteroids game.
+//!
+//! Everything in this library can be simulated without opening a window:
+//! the playfield is described by a [`World`](world::World) carried by the
+//! [`Gamestate`](general::Gamestate), and only the drawing functions need
+//! a graphics context.
+
+// Asteroid
+pub mod asteroid;
+
+// Sound effects and music
+pub mod audio;
+
+// Asteroid classes
+pub mod classes;
+
+// Collisions
+pub mod collision;
+
+// Screen shake, hit-stop and flash
+pub mod effects;
+
+// Game
+pub mod general;
+
+// Spatial partitioning of the collisions
+pub mod grid;
+
+// Table of the best scores
+pub mod highscores;
+
+// Commands of the player
+pub mod input;
+
+// Waves of asteroids
+pub mod level;
+
+// Missile
+pub mod missile;
+
+// Debris, exhaust and trails
+pub mod particles;
+
+// Collisions between asteroids
+pub mod physics;
+
+// Recording and playback of games
+pub mod replay;
+
+// Saving and resuming games
+pub mod save;
+
+// Outlines of the asteroids
+pub mod shape;
+
+// Vaisseau
+pub mod spaceship;
+
+// Flying saucers
+pub mod ufo;
+
+// Gun of the spaceship
+pub mod weapon;
+
+// Playfield
+pub mod world;
@@ -1,27 +1,35 @@
-use general::Gamestate;
 use macroquad::prelude::*;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use asteroid_game::asteroid::TEXTURES;
+use asteroid_game::audio::{self, Audio};
+use asteroid_game::classes::{self, AsteroidClasses};
+use asteroid_game::effects::{CameraEffects, EffectsConfig};
+use asteroid_game::general::Gamestate;
+use asteroid_game::highscores::{HighScore, HighScores};
+use asteroid_game::input::SpeedModifier;
+use asteroid_game::replay::Replay;
+use asteroid_game::save::{load_game, save_game};
+use asteroid_game::world::World;
 
-// Asteroid
-mod asteroid;
-use asteroid::{Asteroid, TEXTURES};
+// Menu
+mod menus;
 
-// Vaisseau
-use spaceship::Spaceship;
-mod spaceship;
+// The longest frame the simulation will catch up with, in seconds
+const MAX_FRAME_TIME: f64 = 0.25;
 
-//missile
-mod missile;
-use missile::Missile;
+// Where the last game is recorded when no path is given
+const DEFAULT_REPLAY: &str = "last.replay";
 
-// Menu
-mod menus;
+// Where the game in progress is saved when quitting
+const DEFAULT_SAVE: &str = "asteroids.save";
 
-// Game
-mod general;
+// Where the best scores are kept
+const DEFAULT_HIGHSCORES: &str = "highscores.txt";
 
-// The precision of the simulation / the duration of one tick
-const TICKS: f64 = 1.0 / 60.0;
+// How much a key press changes the volume
+const VOLUME_STEP: f32 = 0.1;
 
 /// Processes user input and applies changes to the game state.
 ///
@@ -33,164 +41,391 @@ const TICKS: f64 = 1.0 / 60.0;
 /// - `gamestate`: A mutable reference to the [`Gamestate`]. This is required
 ///   to update game elements like the spaceship, asteroids, or game status.
 ///
+/// - `session`: What is kept between games, like the options given on the
+///   command line and the saved game.
+///
 /// # Returns
 /// - `true` to exit the game.
 /// - `false` to continue the game.
-fn handle_input(
-    gamestate: &mut Gamestate
-) -> bool {
+fn handle_input(gamestate: &mut Gamestate, session: &mut Session) -> bool {
     if is_key_pressed(KeyCode::F3) {
         gamestate.debug = !gamestate.debug;
     }
 
     // Gérer les entrées clavier pour contrôler le vaisseau
-    if is_key_down(KeyCode::Up) && gamestate.simulation_speed > 0.0 {
-        gamestate.spaceship.move_spaceship(gamestate.delta_time, true);
-    }
-    if is_key_down(KeyCode::Down) && gamestate.simulation_speed > 0.0 {
-        gamestate.spaceship.move_spaceship(gamestate.delta_time, false);
-    }
-    if is_key_down(KeyCode::Left) && gamestate.simulation_speed > 0.0 {
-        gamestate.spaceship.add_rotation(-gamestate.spaceship.turn_rate * gamestate.delta_time as f32);
-    }
-    if is_key_down(KeyCode::Right) && gamestate.simulation_speed > 0.0 {
-        gamestate.spaceship.add_rotation(gamestate.spaceship.turn_rate * gamestate.delta_time as f32);
-    }
+    // They are applied by the next ticks of the simulation
+    let running = gamestate.simulation_speed > 0.0;
+    gamestate.input.thrust = is_key_down(KeyCode::Up) && running;
+    gamestate.input.reverse = is_key_down(KeyCode::Down) && running;
+    gamestate.input.rotate_left = is_key_down(KeyCode::Left) && running;
+    gamestate.input.rotate_right = is_key_down(KeyCode::Right) && running;
 
     if is_key_pressed(KeyCode::Space) | (is_mouse_button_down(MouseButton::Left) && gamestate.debug)
-        && gamestate.simulation_speed > 0.0
+        && running
     {
-        // Use spaceship's rotation directly for the missile's direction
-        gamestate.missiles.push(Missile::new(
-            gamestate.spaceship.position,
-            gamestate.spaceship.max_speed,
-            gamestate.spaceship.rotation,
-        ))
+        gamestate.input.fire = true;
     }
 
-    if is_key_pressed(KeyCode::S) && gamestate.simulation_speed > 0.0 {
-        gamestate.spaceship.speed = 0.0;
+    if is_key_pressed(KeyCode::S) && running {
+        gamestate.input.stop = true;
     }
 
+    if is_key_pressed(KeyCode::H) && running {
+        gamestate.input.hyperspace = true;
+    }
+
+    gamestate.input.speed = if is_key_down(KeyCode::LeftShift) {
+        SpeedModifier::Slow
+    } else if is_key_down(KeyCode::Tab) {
+        SpeedModifier::Fast
+    } else {
+        SpeedModifier::Normal
+    };
+
     if is_key_down(KeyCode::LeftControl) {
         gamestate.simulation_speed = 0.0;
-    } else if is_key_down(KeyCode::LeftShift) {
-        gamestate.simulation_speed = 0.1;
-    } else if is_key_down(KeyCode::Tab) {
-        gamestate.simulation_speed = 10.0;
+    } else if gamestate.input.speed != SpeedModifier::Normal {
+        gamestate.simulation_speed = gamestate.input.speed.factor();
+    } else if let Some(playback) = &gamestate.playback {
+        // Watch the replay at the pace it was played
+        gamestate.simulation_speed = playback.speed().factor();
     } else {
         gamestate.simulation_speed = if gamestate.game_started { 1.0 } else { 0.0 };
     }
 
+    // Closing the window keeps the game in progress, see Session::quit
+    if is_quit_requested() {
+        return true;
+    }
+
     if !gamestate.game_started {
+        // The name entry and the leaderboard take the keys for themselves
+        if session.handle_screen_input(gamestate) {
+            return false;
+        }
+
         if is_key_pressed(KeyCode::Escape) {
             return true;
         }
 
+        if is_key_pressed(KeyCode::L) {
+            session.screen = Screen::Leaderboard;
+        }
+
         if is_key_pressed(KeyCode::Enter) {
-            gamestate.game_started = true;
-            gamestate.lives = 3; // Réinitialise les vies
-            gamestate.score = 0; // Réinitialise le score
-            gamestate.game_over = false;
-            gamestate.game_won = false;
-            gamestate.asteroids.clear();
-            gamestate.missiles.clear();
-            gamestate.spaceship = Spaceship::new();
-            for _ in 1..=20 {
-                gamestate.asteroids.push(Asteroid::default());
-            }
+            session.start_game(gamestate);
+        }
+
+        if is_key_pressed(KeyCode::C) && session.can_continue {
+            session.resume(gamestate);
         }
     }
 
     if is_key_pressed(KeyCode::Escape) {
+        session.autosave(gamestate);
         gamestate.game_started = false;
+        gamestate.end_replay();
     }
 
     false
 }
 
-/// This function remove elements specifically for asteroids based on a
-/// collection of indices. It does so by first sorting it so that indices
-/// are removed last to first to avoid issues. There must not be duplicates !
-fn remove_asteroid(vector: &mut Vec<Asteroid>, indices: &mut Vec<usize>) {
-    // To prevent a crash when the Vec is modified unexpectedly
-    indices.sort_by(|a, b| b.cmp(a));
-    for &mut index in indices {
-        vector.remove(index);
+/// Mutes the sounds with M and changes their volume with - and =, but on
+/// the name entry where these keys are typed.
+fn handle_audio_input(audio: &mut Audio, session: &Session) {
+    if matches!(session.screen, Screen::NameEntry(_)) {
+        return;
     }
+    if is_key_pressed(KeyCode::M) {
+        audio.settings.muted = !audio.settings.muted;
+    }
+    if is_key_pressed(KeyCode::Minus) {
+        audio.settings.change_volume(-VOLUME_STEP);
+    }
+    if is_key_pressed(KeyCode::Equal) {
+        audio.settings.change_volume(VOLUME_STEP);
+    }
+}
+
+/// The screens shown while no game is running.
+#[derive(Debug, PartialEq)]
+enum Screen {
+    Menu,
+    /// The player types a name for the high score table.
+    NameEntry(String),
+    Leaderboard,
 }
 
-/// Will detect collisions between the spaceship and asteroids and
-/// execute necessary operations to match the expected behaviour.
-fn check_collision_spaceship_asteroid(
-    spaceship: &Spaceship,
-    asteroids: &mut Vec<Asteroid>,
-) -> bool {
-    let mut to_add: Vec<Asteroid> = Vec::new();
-    let mut to_remove_asteroids: Vec<usize> = Vec::new();
-    let mut result: bool = false;
-
-    for asteroid in asteroids.iter_mut().enumerate() {
-        // Calcul de la distance entre le vaisseau et l'astéroïde
-        let distance = (spaceship.position - asteroid.1.position).length();
-
-        let collision_radius =
-            asteroid.1.size as f32 * asteroid.1.scale / 2.0 + spaceship.get_collision_radius();
-
-        // Si la distance est inférieure au rayon de collision, il y a collision
-        if distance < collision_radius {
-            to_add = asteroid.1.split(true);
-            to_remove_asteroids.push(asteroid.0);
-            result = true;
-        }
-    }
-    remove_asteroid(asteroids, &mut to_remove_asteroids);
-    asteroids.extend(to_add);
-    result
+/// What is kept between games: the options given on the command line,
+/// the files the game writes and the screen shown between games.
+struct Session {
+    seed: Option<u64>,
+    replay: Option<Replay>,
+    record_path: PathBuf,
+    save_path: PathBuf,
+    can_continue: bool,
+    /// Whether the game running was continued from the save or saved into
+    /// it, the only game whose end removes the save.
+    game_in_save: bool,
+    highscores_path: PathBuf,
+    highscores: HighScores,
+    screen: Screen,
+    notice: Option<String>,
 }
 
-/// Will detect collisions between missiles and asteroids and
-/// execute necessary operations to match the expected behaviour.
-fn check_collision_asteroid_missile(
-    gamestate: &mut Gamestate
-) {
-    let mut to_remove_asteroids = Vec::new();
-    let mut to_remove_missiles = Vec::new();
-    let size_to_score = Vec::from([3, 2, 1]);
-    let mut to_add: Vec<Asteroid> = Vec::new();
-
-    for (asteroid_index, asteroid) in gamestate.asteroids.iter_mut().enumerate() {
-        for (missile_index, missile) in gamestate.missiles.iter_mut().enumerate() {
-            let distance = (asteroid.position - missile.position).length();
-            let collision_radius = asteroid.size as f32 * asteroid.scale / 2.0 + missile.size;
-
-            if missile.active && distance < collision_radius {
-                to_add.extend(asteroid.split(gamestate.number_of_asteroids < gamestate.asteroid_limit));
-                // Prevent crash by not duplicating the index
-                if !to_remove_asteroids.contains(&asteroid_index) {
-                    // Marque l'astéroïde pour suppression
-                    to_remove_asteroids.push(asteroid_index);
+impl Session {
+    fn new(arguments: Arguments) -> Self {
+        let replay = arguments.replay.and_then(|path| match Replay::load(&path) {
+            Ok(replay) => {
+                println!("[INFO]: Loaded replay: {}", path.display());
+                Some(replay)
+            }
+            Err(error) => {
+                println!("[ERROR]: {error}");
+                None
+            }
+        });
+        let save_path = PathBuf::from(DEFAULT_SAVE);
+        let highscores_path = PathBuf::from(DEFAULT_HIGHSCORES);
+        let (highscores, warning) = HighScores::load(&highscores_path);
+        if let Some(warning) = &warning {
+            println!("[WARN]: {warning}");
+        }
+        Self {
+            seed: arguments.seed,
+            replay,
+            record_path: arguments
+                .record
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_REPLAY)),
+            can_continue: save_path.exists(),
+            game_in_save: false,
+            save_path,
+            highscores_path,
+            highscores,
+            screen: Screen::Menu,
+            notice: warning,
+        }
+    }
+
+    /// Handles the keys of the name entry and of the leaderboard.
+    /// Returns `false` on the menu, where the keys are left to the caller.
+    fn handle_screen_input(&mut self, gamestate: &Gamestate) -> bool {
+        match &mut self.screen {
+            Screen::Menu => false,
+            Screen::NameEntry(name) => {
+                // The queue gives the last typed character first
+                let mut typed = Vec::new();
+                while let Some(character) = get_char_pressed() {
+                    typed.push(character);
+                }
+                for character in typed.into_iter().rev() {
+                    if !character.is_control() && name.chars().count() < HighScore::MAX_NAME_LENGTH
+                    {
+                        name.push(character);
+                    }
                 }
-                if !to_remove_missiles.contains(&missile_index) {
-                    // Marque le missile pour suppression
-                    to_remove_missiles.push(missile_index);
+                if is_key_pressed(KeyCode::Backspace) {
+                    name.pop();
                 }
-                // The score gained depends on the properties of the asteroid (TO DO)
-                gamestate.score += 100 * size_to_score[asteroid.size as usize - 1];
-                // On ne vérifie plus ce missile pour cet astéroïde
-                break;
+                if is_key_pressed(KeyCode::Enter) {
+                    let entry = HighScore::new(name, gamestate.score, gamestate.seed);
+                    self.highscores.insert(entry);
+                    if let Err(error) = self.highscores.save(&self.highscores_path) {
+                        self.report(format!("Cannot save the high scores: {error}"));
+                    }
+                    self.screen = Screen::Leaderboard;
+                } else if is_key_pressed(KeyCode::Escape) {
+                    self.screen = Screen::Menu;
+                }
+                true
             }
+            Screen::Leaderboard => {
+                if is_key_pressed(KeyCode::Escape)
+                    || is_key_pressed(KeyCode::Enter)
+                    || is_key_pressed(KeyCode::L)
+                {
+                    self.screen = Screen::Menu;
+                }
+                true
+            }
+        }
+    }
+
+    /// Starts a new game, or watches the replay given on the command line.
+    /// The save is left for the game it belongs to.
+    fn start_game(&mut self, gamestate: &mut Gamestate) {
+        self.game_in_save = false;
+        if let Some(replay) = self.replay.clone() {
+            if let Err(error) = gamestate.start_playback(replay) {
+                self.report(error.to_string());
+            }
+        } else {
+            gamestate.seed = self.seed.unwrap_or_else(::rand::random);
+            gamestate.new_game();
         }
     }
 
-    // Ajuste le vecteur en enlevant les missiles et les astéroides détruits et en rajoutant les petits
-    remove_asteroid(&mut gamestate.asteroids, &mut to_remove_asteroids);
-    to_remove_missiles.sort_by(|a, b| b.cmp(a));
-    for idx in to_remove_missiles {
-        gamestate.missiles.remove(idx);
+    /// Saves the game in progress so that it can be continued later.
+    /// A replay being watched is not saved.
+    fn autosave(&mut self, gamestate: &mut Gamestate) {
+        if !gamestate.game_started || gamestate.playback.is_some() {
+            return;
+        }
+        match save_game(gamestate, &self.save_path) {
+            Ok(()) => {
+                println!("[INFO]: Saved game: {}", self.save_path.display());
+                self.can_continue = true;
+                self.game_in_save = true;
+            }
+            Err(error) => self.report(error.to_string()),
+        }
+    }
+
+    /// Continues the saved game.
+    fn resume(&mut self, gamestate: &mut Gamestate) {
+        match load_game(gamestate, &self.save_path) {
+            Ok(()) => {
+                self.notice = None;
+                self.game_in_save = true;
+            }
+            Err(error) => {
+                self.can_continue = false;
+                self.report(error.to_string());
+            }
+        }
+    }
+
+    /// Writes the files of a game that just ended: its replay, and no save
+    /// anymore if it was the one saved since there is nothing left to
+    /// continue. A good enough score
+    /// opens the name entry for the high score table.
+    fn game_ended(&mut self, gamestate: &mut Gamestate) {
+        let watched_replay = gamestate.playback_result.is_some();
+        if let Some(finished_replay) = gamestate.finished_replay.take() {
+            match finished_replay.save(&self.record_path) {
+                Ok(()) => println!("[INFO]: Saved replay: {}", self.record_path.display()),
+                Err(error) => self.report(error.to_string()),
+            }
+        }
+        if let Some(result) = gamestate.playback_result.take() {
+            match result {
+                Ok(()) => self.notice = Some("Replay verified".to_string()),
+                Err(error) => self.report(error.to_string()),
+            }
+        }
+        if !(gamestate.game_over || gamestate.game_won) {
+            return;
+        }
+        if self.game_in_save {
+            self.game_in_save = false;
+            self.can_continue = false;
+            if let Err(error) = std::fs::remove_file(&self.save_path) {
+                println!("[WARN]: Cannot remove the save: {error}");
+            }
+        }
+        if !watched_replay && self.highscores.qualifies(gamestate.score) {
+            // Forget what was typed during the game
+            clear_input_queue();
+            self.screen = Screen::NameEntry(String::new());
+        }
+    }
+
+    /// Keeps the game in progress whichever way the game is left: it is
+    /// saved to be continued, and its replay is written.
+    fn quit(&mut self, gamestate: &mut Gamestate) {
+        self.autosave(gamestate);
+        if gamestate.game_started {
+            gamestate.game_started = false;
+            gamestate.end_replay();
+            self.game_ended(gamestate);
+        }
+    }
+
+    /// Shows an error on the menu.
+    fn report(&mut self, error: String) {
+        println!("[ERROR]: {error}");
+        self.notice = Some(error);
     }
-    gamestate.asteroids.extend(to_add);
-    gamestate.number_of_asteroids = gamestate.asteroids.len();
+}
+
+/// The options given on the command line.
+#[derive(Debug, Default, PartialEq)]
+struct Arguments {
+    /// `--seed <n>`: the seed of every game.
+    seed: Option<u64>,
+    /// `--record <path>`: where the last game is recorded.
+    record: Option<PathBuf>,
+    /// `--replay <path>`: a replay to watch instead of playing.
+    replay: Option<PathBuf>,
+    /// `--campaign <levels>`: the game is won after this many levels
+    /// instead of going on endlessly.
+    campaign: Option<u32>,
+    /// `--vector`: jagged asteroids drawn as lines, colliding by their outline.
+    vector: bool,
+    /// `--bounce`: asteroids bouncing off each other, breaking on hard impacts.
+    bounce: bool,
+    /// `--no-shake`, `--no-hit-stop` and `--no-flash`: turn off an effect
+    /// of the camera.
+    no_shake: bool,
+    no_hit_stop: bool,
+    no_flash: bool,
+}
+
+/// Reads the options from the command line arguments, each given either
+/// as `--option <value>` or `--option=<value>`, but for the flags which take
+/// no value. Unknown arguments are ignored.
+fn parse_arguments(args: &[String]) -> Result<Arguments, String> {
+    let mut arguments = Arguments::default();
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        let flag = match name {
+            "--vector" => Some(&mut arguments.vector),
+            "--bounce" => Some(&mut arguments.bounce),
+            "--no-shake" => Some(&mut arguments.no_shake),
+            "--no-hit-stop" => Some(&mut arguments.no_hit_stop),
+            "--no-flash" => Some(&mut arguments.no_flash),
+            _ => None,
+        };
+        if let Some(flag) = flag {
+            *flag = true;
+            continue;
+        }
+        if !["--seed", "--record", "--replay", "--campaign"].contains(&name) {
+            continue;
+        }
+        let value = match inline_value {
+            Some(value) => value,
+            None => iter
+                .next()
+                .cloned()
+                .ok_or(format!("{name} needs a value"))?,
+        };
+        match name {
+            "--seed" => {
+                arguments.seed = Some(
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| format!("Invalid seed: {value}"))?,
+                )
+            }
+            "--campaign" => {
+                arguments.campaign = Some(
+                    value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|levels| *levels > 0)
+                        .ok_or(format!("Invalid number of levels: {value}"))?,
+                )
+            }
+            "--record" => arguments.record = Some(PathBuf::from(value)),
+            _ => arguments.replay = Some(PathBuf::from(value)),
+        }
+    }
+    Ok(arguments)
 }
 
 /*
@@ -206,7 +441,7 @@ Altough it's outdated and vastly different
 /// - Updates the game state (e.g., physics, collisions, and game logic).
 /// - Renders the game visuals, including the background, asteroids, spaceship, and missiles.
 ///
-/// The game loop is designed to be frame-rate independent using a delta-time (DT) system. 
+/// The game loop is designed to be frame-rate independent using a fixed timestep.
 /// This ensures smooth animations and consistent behavior, regardless of the frame rate.
 ///
 /// # Features
@@ -220,9 +455,50 @@ Altough it's outdated and vastly different
 ///   - Ends the game when the player loses all lives or destroys all asteroids.
 ///   - Manages transitions to the main menu or victory screen.
 ///
-/// # Delta-Time System
-/// The delta-time (DT) system ensures physics calculations and movements are time-based rather than 
-/// frame-based. This provides consistent gameplay across varying system performance.
+/// # Fixed Timestep
+/// The time of each frame, scaled by the simulation speed, is consumed in ticks of
+/// [`TICKS`](asteroid_game::general::TICKS) seconds: every movement and collision happens
+/// inside a tick, so the physics is identical at 30, 60 or 240 FPS. The rendering is
+/// interpolated between the last two ticks to stay smooth at any speed.
+///
+/// # Seed
+/// The field is generated from a seed shown in the debug overlay (F3).
+/// Launch the game with `--seed <n>` to replay the exact same field.
+///
+/// # Replays
+/// Every game is recorded and saved when it ends, to `--record <path>` or
+/// to `last.replay`. Launch the game with `--replay <path>` to watch one: the
+/// end of the playback is checked against the recorded score and cycle. A
+/// replay is refused if `assets/asteroids.cfg` changed since it was recorded.
+///
+/// # Vector Asteroids
+/// Launch the game with `--vector` for jagged asteroids drawn as lines like
+/// the arcade game. A replay is watched with the asteroids it was recorded
+/// with, whatever the option.
+///
+/// # Bouncing Asteroids
+/// Launch the game with `--bounce` for asteroids colliding with each other:
+/// they bounce off by their mass, and the hardest impacts break both. A
+/// replay bounces as it was recorded, whatever the option.
+///
+/// # Camera Effects
+/// The screen shakes with the explosions, and losing the spaceship freezes
+/// the game for a few frames with a red flash. Launch the game with
+/// `--no-shake`, `--no-hit-stop` or `--no-flash` to turn one of them off.
+///
+/// # Sound
+/// Built with `--features audio`, the game plays the sounds of
+/// `assets/sounds`: press M to mute them, - and = to change the volume.
+/// Without the feature, an output device or the files, the game is silent.
+///
+/// # Saves
+/// Quitting during a game saves it to `asteroids.save`, and the menu offers
+/// to continue it, with the options it was played with. The save is removed
+/// once that game is over.
+///
+/// # High Scores
+/// A game over or a victory with one of the best scores asks for a name, and
+/// the table is kept in `highscores.txt`. Press L on the menu to see it.
 ///
 /// # Window Initialization
 /// - The game starts in fullscreen mode.
@@ -244,7 +520,40 @@ async fn main() {
         next_frame().await
     }
 
-    let mut gamestate = Gamestate::new();
+    let arguments = match parse_arguments(&std::env::args().collect::<Vec<String>>()) {
+        Ok(arguments) => arguments,
+        Err(error) => {
+            println!("[WARN]: {error}, the options are ignored");
+            Arguments::default()
+        }
+    };
+    let campaign = arguments.campaign;
+    let vector = arguments.vector;
+    let bounce = arguments.bounce;
+    let mut effects = CameraEffects::new(EffectsConfig {
+        shake: !arguments.no_shake,
+        hit_stop: !arguments.no_hit_stop,
+        flash: !arguments.no_flash,
+        ..EffectsConfig::default()
+    });
+    let mut session = Session::new(arguments);
+    // Closing the window goes through handle_input to save the game
+    prevent_quit();
+
+    let mut gamestate = Gamestate::new(
+        World::new(screen_width(), screen_height()),
+        session.seed.unwrap_or_else(::rand::random),
+    );
+    gamestate.level_config.campaign = campaign;
+    gamestate.shape_config.enabled = vector;
+    gamestate.physics_config.enabled = bounce;
+    gamestate.asteroid_classes = match AsteroidClasses::load(Path::new(classes::DEFAULT_PATH)) {
+        Ok(classes) => classes,
+        Err(error) => {
+            println!("[WARN]: {error}, the built-in classes are used");
+            AsteroidClasses::default()
+        }
+    };
 
     let mut textures = HashMap::new();
     for texture in TEXTURES.iter() {
@@ -260,78 +569,60 @@ async fn main() {
         .expect("Failed to load background texture");
     background_texture.set_filter(FilterMode::Nearest);
 
+    let mut audio = match Audio::load(audio::DEFAULT_DIRECTORY).await {
+        Ok(audio) if audio.is_enabled() => {
+            println!("[INFO]: Loaded sounds: {}", audio::DEFAULT_DIRECTORY);
+            audio
+        }
+        Ok(audio) => {
+            println!("[INFO]: No sound output, the game is silent");
+            audio
+        }
+        Err(error) => {
+            println!("[WARN]: Cannot load the sounds: {error}, the game is silent");
+            Audio::silent()
+        }
+    };
+
     let mut previous_time = 0.0;
-    let mut accumulator = 0.0;
     let mut fps_cooldown = get_time();
     let mut fps = macroquad::time::get_fps() as u32;
 
     loop {
-        gamestate.delta_time = (get_time() - previous_time) * gamestate.simulation_speed;
+        let was_started = gamestate.game_started;
+
+        // Limit a long frame (e.g. a moved window) to a few ticks
+        let frame_time = (get_time() - previous_time).min(MAX_FRAME_TIME);
         previous_time = get_time();
 
-        if handle_input(&mut gamestate) {
+        if handle_input(&mut gamestate, &mut session) {
             println!("Exiting the game...");
+            session.quit(&mut gamestate);
             break;
         }
-
-        gamestate.missiles.retain(|m| m.active);
+        handle_audio_input(&mut audio, &session);
 
         if get_time() - fps_cooldown >= 0.25 {
             fps = macroquad::time::get_fps() as u32;
             fps_cooldown = get_time()
         }
 
-        let mut asteroid_index_to_remove: Vec<usize> = Vec::new();
-
-        // Jeu principal
-        if check_collision_spaceship_asteroid(&gamestate.spaceship, &mut gamestate.asteroids) {
-            gamestate.lives = gamestate.lives.saturating_sub(1);
-            // Réinitialise la position du vaisseau
-            gamestate.spaceship = Spaceship::new();
-        }
-
-        // Si le joueur perd ses 3 vies, revenir au menu principal
-        if gamestate.lives == 0 {
-            gamestate.game_started = false;
-            gamestate.game_over = true;
-            // Retour au menu principal
-        }
-
-        accumulator += gamestate.delta_time;
-        while accumulator >= gamestate.delta_time {
-            gamestate.loop_number += 1;
-
-            for asteroid in &mut gamestate.asteroids.iter_mut().enumerate() {
-                if asteroid.1.size == 0 && !asteroid_index_to_remove.contains(&asteroid.0) {
-                    asteroid_index_to_remove.push(asteroid.0);
-                }
-            }
-
-            let mut to_add: Vec<Asteroid> = Vec::new();
-            for index in &asteroid_index_to_remove {
-                if gamestate.number_of_asteroids <= gamestate.asteroid_limit {
-                    to_add.extend(gamestate.asteroids[*index].split(gamestate.number_of_asteroids < gamestate.asteroid_limit))
-                };
-            }
-
-            gamestate.asteroids.extend(to_add);
-            gamestate.number_of_asteroids = gamestate.asteroids.len();
-
-           check_collision_asteroid_missile(&mut gamestate);
-            accumulator -= TICKS;
-        }
-
-        for asteroid in &mut gamestate.asteroids {
-            asteroid.add_rotation(asteroid.turn_rate * gamestate.delta_time as f32);
-            asteroid.move_object(gamestate.delta_time);
+        // The playfield follows the size of the window between games,
+        // a game keeps its own so that it can be replayed
+        if !gamestate.game_started {
+            gamestate.world = World::new(screen_width(), screen_height());
         }
-        gamestate.spaceship.update(gamestate.delta_time);
-        for missile in &mut gamestate.missiles {
-            missile.update(gamestate.delta_time);
+        // The game stays frozen for a moment when the spaceship is destroyed
+        let simulated_time = if effects.is_frozen() { 0.0 } else { frame_time };
+        gamestate.update(simulated_time);
+        effects.update(&gamestate, frame_time as f32);
+        audio.update(&gamestate);
+        let alpha = gamestate.alpha();
+
+        if was_started && !gamestate.game_started {
+            session.game_ended(&mut gamestate);
         }
 
-        remove_asteroid(&mut gamestate.asteroids, &mut asteroid_index_to_remove);
-
         // Affichages
         if !gamestate.debug {
         draw_texture_ex(
@@ -344,36 +635,185 @@ async fn main() {
                 ..Default::default()
             },
         );}
+        // The playfield shakes, not the menus drawn over it
+        set_camera(&effects.camera(screen_width(), screen_height()));
 
         for asteroid in &mut gamestate.asteroids.iter_mut().enumerate() {
             asteroid.1.draw_self(
                 textures.get(&asteroid.1.texture.to_string()).unwrap(),
                 gamestate.debug,
+                alpha,
+                &gamestate.world,
+                &gamestate.asteroid_classes,
             );
         }
-        gamestate.spaceship.draw(25.0, gamestate.debug);
+        gamestate.particles.draw(alpha, &gamestate.world);
+        if !gamestate.is_respawning() {
+            gamestate
+                .spaceship
+                .draw(25.0, gamestate.debug, alpha, &gamestate.world);
+        }
         for missile in &gamestate.missiles {
-            missile.draw();
+            missile.draw(alpha, &gamestate.world);
         }
+        for ufo in &gamestate.ufos {
+            ufo.draw(alpha, &gamestate.world);
+        }
+        for missile in &gamestate.ufo_missiles {
+            missile.draw_colored(alpha, &gamestate.world, LIME);
+        }
+        set_default_camera();
+        effects.draw_flash(screen_width(), screen_height());
 
         if !gamestate.game_started {
-            menus::menu_draw(screen_width(), screen_height(), gamestate.game_over, gamestate.game_won);
+            match &session.screen {
+                Screen::Menu => menus::menu_draw(
+                    screen_width(),
+                    screen_height(),
+                    gamestate.game_over,
+                    gamestate.game_won,
+                    session.can_continue,
+                    session.notice.as_deref(),
+                ),
+                Screen::NameEntry(name) => {
+                    menus::draw_name_entry(screen_width(), screen_height(), name, gamestate.score)
+                }
+                Screen::Leaderboard => {
+                    menus::draw_leaderboard(screen_width(), screen_height(), &session.highscores)
+                }
+            }
         } else {
-            menus::draw_simulation(
-                gamestate.debug,
-                gamestate.loop_number,
-                gamestate.number_of_asteroids,
-                fps,
-                gamestate.score,
-                gamestate.simulation_speed,
-            );
-
-            if gamestate.asteroids.is_empty() {
-                gamestate.game_won = true;
-                gamestate.game_started = false;
+            menus::draw_simulation(&gamestate, fps);
+            if gamestate.intermission > 0.0 {
+                menus::draw_intermission(
+                    screen_width(),
+                    screen_height(),
+                    gamestate.level,
+                    gamestate.intermission,
+                );
             }
         }
 
         next_frame().await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_seed() {
+        let seed = |list: &[&str]| parse_arguments(&args(list)).map(|a| a.seed);
+        assert_eq!(seed(&["game"]), Ok(None));
+        assert_eq!(seed(&["game", "--seed", "42"]), Ok(Some(42)));
+        assert_eq!(seed(&["game", "--seed=7"]), Ok(Some(7)));
+        assert!(seed(&["game", "--seed", "abc"]).is_err());
+        assert!(seed(&["game", "--seed"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_replay_paths() {
+        let arguments =
+            parse_arguments(&args(&["game", "--record", "a.replay", "--replay=b.replay"]))
+                .unwrap();
+        assert_eq!(arguments.record, Some(PathBuf::from("a.replay")));
+        assert_eq!(arguments.replay, Some(PathBuf::from("b.replay")));
+        assert_eq!(arguments.seed, None);
+    }
+
+    #[test]
+    fn test_parse_campaign() {
+        let campaign = |list: &[&str]| parse_arguments(&args(list)).map(|a| a.campaign);
+        assert_eq!(campaign(&["game"]), Ok(None));
+        assert_eq!(campaign(&["game", "--campaign", "10"]), Ok(Some(10)));
+        assert!(campaign(&["game", "--campaign=0"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_vector() {
+        let vector = |list: &[&str]| parse_arguments(&args(list)).map(|a| a.vector);
+        assert_eq!(vector(&["game"]), Ok(false));
+        assert_eq!(vector(&["game", "--vector", "--seed", "1"]), Ok(true));
+    }
+
+    #[test]
+    fn test_parse_effects() {
+        let arguments = parse_arguments(&args(&["game", "--no-shake", "--no-flash"])).unwrap();
+        assert!(arguments.no_shake && arguments.no_flash);
+        assert!(!arguments.no_hit_stop);
+    }
+
+    #[test]
+    fn test_parse_bounce() {
+        let bounce = |list: &[&str]| parse_arguments(&args(list)).map(|a| a.bounce);
+        assert_eq!(bounce(&["game", "--vector"]), Ok(false));
+        assert_eq!(bounce(&["game", "--seed=1", "--bounce"]), Ok(true));
+    }
+
+    fn session(name: &str) -> Session {
+        let directory = std::env::temp_dir();
+        Session {
+            seed: Some(1),
+            replay: None,
+            record_path: directory.join(format!("{name}.replay")),
+            save_path: directory.join(format!("{name}.save")),
+            can_continue: false,
+            game_in_save: false,
+            highscores_path: directory.join(format!("{name}_highscores.txt")),
+            highscores: HighScores::default(),
+            screen: Screen::Menu,
+            notice: None,
+        }
+    }
+
+    #[test]
+    fn test_lost_game_keeps_the_other_save() {
+        let mut session = session("asteroids_test_session");
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+
+        // La partie A est sauvegardée en quittant
+        session.start_game(&mut gamestate);
+        gamestate.update(0.1);
+        session.autosave(&mut gamestate);
+        gamestate.game_started = false;
+        assert!(session.can_continue && session.save_path.exists());
+
+        // La partie B est perdue, la sauvegarde de A reste
+        session.start_game(&mut gamestate);
+        gamestate.game_started = false;
+        gamestate.game_over = true;
+        session.game_ended(&mut gamestate);
+        assert!(session.can_continue && session.save_path.exists());
+
+        // A est reprise puis perdue, il n'y a plus rien à continuer
+        session.resume(&mut gamestate);
+        gamestate.game_started = false;
+        gamestate.game_over = true;
+        session.game_ended(&mut gamestate);
+        assert!(!session.can_continue && !session.save_path.exists());
+    }
+
+    #[test]
+    fn test_quit_keeps_save_and_replay() {
+        let mut session = session("asteroids_test_quit");
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 0);
+        let _ = std::fs::remove_file(&session.record_path);
+
+        // Fermer la fenêtre en pleine partie
+        session.start_game(&mut gamestate);
+        gamestate.update(0.1);
+        session.quit(&mut gamestate);
+        assert!(!gamestate.game_started);
+        assert!(session.save_path.exists());
+        let replay = Replay::load(&session.record_path).unwrap();
+        assert_eq!(replay.inputs.len(), gamestate.loop_number as usize);
+
+        std::fs::remove_file(&session.save_path).unwrap();
+        std::fs::remove_file(&session.record_path).unwrap();
+    }
+}
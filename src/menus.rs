@@ -1,8 +1,17 @@
+use asteroid_game::general::Gamestate;
+use asteroid_game::highscores::HighScores;
 use chrono::Local;
 use macroquad::prelude::*;
 
 /// Draw all the elements that appear on screen at different stages.
-pub fn menu_draw(screen_width: f32, screen_height: f32, game_over: bool, game_won: bool) {
+pub fn menu_draw(
+    screen_width: f32,
+    screen_height: f32,
+    game_over: bool,
+    game_won: bool,
+    can_continue: bool,
+    notice: Option<&str>,
+) {
     clear_background(BLACK);
     draw_text(
         "ASTEROIDS",
@@ -25,6 +34,34 @@ pub fn menu_draw(screen_width: f32, screen_height: f32, game_over: bool, game_wo
         30.0,
         GRAY,
     );
+    draw_text(
+        "Press L for high scores",
+        screen_width / 2.0 - measure_text("Press L for high scores", None, 30, screen_dpi_scale()).width / 2.0,
+        screen_height / 2.0 + 100.0,
+        30.0,
+        GRAY,
+    );
+
+    if can_continue {
+        draw_text(
+            "Press C to continue",
+            screen_width / 2.0 - measure_text("Press C to continue", None, 30, screen_dpi_scale()).width / 2.0,
+            screen_height / 2.0 + 150.0,
+            30.0,
+            GRAY,
+        );
+    }
+
+    // Errors and messages about the files of the game
+    if let Some(notice) = notice {
+        draw_text(
+            notice,
+            screen_width / 2.0 - measure_text(notice, None, 24, screen_dpi_scale()).width / 2.0,
+            screen_height / 2.0 + 225.0,
+            24.0,
+            ORANGE,
+        );
+    }
 
     if game_over {
         draw_text(
@@ -45,15 +82,126 @@ pub fn menu_draw(screen_width: f32, screen_height: f32, game_over: bool, game_wo
     }
 }
 
+/// Draw the screen where the player types a name for the high score table.
+pub fn draw_name_entry(screen_width: f32, screen_height: f32, name: &str, score: u128) {
+    clear_background(BLACK);
+    let score_text = format!("Score:{}", score);
+    // Blinking cursor
+    let name_text = if get_time().fract() < 0.5 {
+        format!("{}_", name)
+    } else {
+        format!("{} ", name)
+    };
+    let lines = [
+        ("NEW HIGH SCORE", -150.0, 60, GOLD),
+        (score_text.as_str(), -80.0, 40, WHITE),
+        ("Enter your name:", 0.0, 30, GRAY),
+        (name_text.as_str(), 50.0, 40, WHITE),
+        ("ENTER to confirm, Esc to skip", 120.0, 24, GRAY),
+    ];
+    for (text, y_offset, font_size, color) in lines {
+        draw_text(
+            text,
+            screen_width / 2.0 - measure_text(text, None, font_size, screen_dpi_scale()).width / 2.0,
+            screen_height / 2.0 + y_offset,
+            font_size as f32,
+            color,
+        );
+    }
+}
+
+/// Draw the high score table.
+pub fn draw_leaderboard(screen_width: f32, screen_height: f32, highscores: &HighScores) {
+    clear_background(BLACK);
+    draw_text(
+        "HIGH SCORES",
+        screen_width / 2.0 - measure_text("HIGH SCORES", None, 60, screen_dpi_scale()).width / 2.0,
+        screen_height / 4.0,
+        60.0,
+        GOLD,
+    );
+
+    if highscores.entries.is_empty() {
+        draw_text(
+            "No score yet",
+            screen_width / 2.0 - measure_text("No score yet", None, 30, screen_dpi_scale()).width / 2.0,
+            screen_height / 2.0,
+            30.0,
+            GRAY,
+        );
+    }
+
+    // Rank, name, score, date and seed in columns
+    let left = screen_width / 2.0 - 400.0;
+    let columns = [0.0, 60.0, 320.0, 500.0, 670.0];
+    for (index, entry) in highscores.entries.iter().enumerate() {
+        let y = screen_height / 4.0 + 80.0 + index as f32 * 40.0;
+        let fields = [
+            format!("{}.", index + 1),
+            entry.name.clone(),
+            entry.score.to_string(),
+            entry.date.format("%Y-%m-%d").to_string(),
+            format!("#{}", entry.seed),
+        ];
+        for (field, column) in fields.iter().zip(columns) {
+            draw_text(field, left + column, y, 30.0, if index == 0 { GOLD } else { WHITE });
+        }
+    }
+
+    draw_text(
+        "Press Esc to go back",
+        screen_width / 2.0 - measure_text("Press Esc to go back", None, 24, screen_dpi_scale()).width / 2.0,
+        screen_height - 60.0,
+        24.0,
+        GRAY,
+    );
+}
+
+/// Draw the announcement of the next wave between two levels.
+pub fn draw_intermission(screen_width: f32, screen_height: f32, level: u32, time_left: f32) {
+    let cleared = format!("LEVEL {} CLEARED", level.saturating_sub(1));
+    draw_text(
+        &cleared,
+        screen_width / 2.0 - measure_text(&cleared, None, 60, screen_dpi_scale()).width / 2.0,
+        screen_height / 2.0 - 50.0,
+        60.0,
+        GREEN,
+    );
+    let next = format!("Level {} in {}", level, time_left.ceil() as u32);
+    draw_text(
+        &next,
+        screen_width / 2.0 - measure_text(&next, None, 40, screen_dpi_scale()).width / 2.0,
+        screen_height / 2.0 + 20.0,
+        40.0,
+        WHITE,
+    );
+}
+
+/// Draw a small spaceship pointing up, centered on (`x`, `y`).
+fn draw_ship_icon(x: f32, y: f32, size: f32) {
+    draw_triangle(
+        vec2(x, y - size),
+        vec2(x - size * 0.7, y + size),
+        vec2(x + size * 0.7, y + size),
+        YELLOW,
+    );
+}
+
 /// Draw the debug interface and information about the game state
-pub fn draw_simulation(
-    debug: bool,
-    cycle: u128,
-    number_of_asteroids: usize,
-    fps: u32,
-    score: u128,
-    simulation_speed: f64,
-) {
+pub fn draw_simulation(gamestate: &Gamestate, fps: u32) {
+    let debug = gamestate.debug;
+    let cycle = gamestate.loop_number;
+    let number_of_asteroids = gamestate.number_of_asteroids;
+    let score = gamestate.score;
+    let simulation_speed = gamestate.simulation_speed;
+    let seed = gamestate.seed;
+    let shield = gamestate.spaceship.shield;
+    let max_shield = gamestate.shield_config.max_shield;
+    let weapon = gamestate.spaceship.weapon;
+    let level = gamestate.level;
+    let lives = gamestate.lives;
+    let extra_life_notice = gamestate.extra_life_notice;
+
     if debug {
         draw_text(
             &(format!("Cycle:{}", cycle)).to_string(),
@@ -76,6 +224,7 @@ pub fn draw_simulation(
             48.0,
             YELLOW,
         );
+        draw_text(&(format!("Seed:{}", seed)), 10.0, 250.0, 48.0, ORANGE);
         draw_text(
             &(format!("Speed factor:{}x", simulation_speed)),
             (screen_width()
@@ -119,4 +268,59 @@ pub fn draw_simulation(
         48.0,
         WHITE,
     );
+
+    draw_text(
+        &format!("Level:{}", level),
+        screen_width() - measure_text(&format!("Level:{}", level), None, 48, 1.0).width - 10.0,
+        165.0,
+        48.0,
+        WHITE,
+    );
+
+    // One ship per life under the level
+    for life in 0..lives {
+        draw_ship_icon(screen_width() - 25.0 - life as f32 * 30.0, 190.0, 12.0);
+    }
+    if extra_life_notice > 0.0 {
+        draw_text(
+            "EXTRA LIFE",
+            (screen_width() - measure_text("EXTRA LIFE", None, 60, screen_dpi_scale()).width) / 2.0,
+            screen_height() / 4.0,
+            60.0,
+            GREEN,
+        );
+    }
+
+    // Shield bar under the score
+    let bar_width = 200.0;
+    let bar_x = screen_width() - bar_width - 10.0;
+    let ratio = if max_shield > 0.0 {
+        (shield / max_shield).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let bar_color = if ratio > 0.5 {
+        SKYBLUE
+    } else if ratio > 0.0 {
+        ORANGE
+    } else {
+        RED
+    };
+    draw_text("Shield", bar_x - 90.0, 88.0, 32.0, WHITE);
+    draw_rectangle(bar_x, 70.0, bar_width * ratio, 20.0, bar_color);
+    draw_rectangle_lines(bar_x, 70.0, bar_width, 20.0, 2.0, WHITE);
+
+    // Heat bar under the shield, red while the gun cools down from overheating
+    if weapon.overheat {
+        let heat_color = if weapon.overheated { RED } else { GOLD };
+        draw_text("Heat", bar_x - 90.0, 118.0, 32.0, WHITE);
+        draw_rectangle(
+            bar_x,
+            100.0,
+            bar_width * weapon.heat.clamp(0.0, 1.0),
+            20.0,
+            heat_color,
+        );
+        draw_rectangle_lines(bar_x, 100.0, bar_width, 20.0, 2.0, WHITE);
+    }
 }
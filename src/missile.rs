@@ -1,43 +1,94 @@
 use macroquad::prelude::*;
 
+use crate::world::World;
+
+/// The rules of the missiles, shared by every missile of a game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MissileConfig {
+    /// Speed relative to the spaceship, in px/s.
+    pub speed: f32,
+    /// Seconds before a missile expires.
+    pub lifetime: f32,
+    /// Distance before a missile expires, `None` to only use the lifetime.
+    pub max_distance: Option<f32>,
+}
+
+impl Default for MissileConfig {
+    fn default() -> Self {
+        Self {
+            speed: 700.0,
+            lifetime: 1.0,
+            max_distance: None,
+        }
+    }
+}
+
 pub struct Missile {
     pub position: Vec2,
-    pub velocity: f32,
+    pub velocity: Vec2,
     pub rotation: f32,
     pub active: bool,
     pub size: f32,
+    // Seconds since the shot and distance covered, to expire the missile
+    pub age: f32,
+    pub traveled: f32,
+    pub lifetime: f32,
+    pub max_distance: Option<f32>,
+    // Position at the previous tick, for the interpolation of the rendering
+    pub previous_position: Vec2,
 }
 
 impl Missile {
-    /// Crée un nouveau missile à une position donnée avec une vitesse donnée.
-    pub fn new(position: Vec2, direction: f32, rotation: f32) -> Self {
+    /// Crée un nouveau missile tiré dans la direction `rotation`, qui garde
+    /// la vitesse du vaisseau qui le tire.
+    pub fn new(position: Vec2, ship_velocity: Vec2, rotation: f32, config: &MissileConfig) -> Self {
         Self {
             position,
-            velocity: direction.abs(),
+            velocity: ship_velocity + Vec2::from_angle(rotation) * config.speed,
             rotation,
             active: true,
             size: 5.0,
+            age: 0.0,
+            traveled: 0.0,
+            lifetime: config.lifetime,
+            max_distance: config.max_distance,
+            previous_position: position,
         }
     }
 
-    /// Met à jour la position du missile. Désactive le missile s'il sort de l'écran.
-    pub fn update(&mut self, delta_time: f64) {
-        //cos pour x, sin pour y
-        let direction_vec = vec2(self.rotation.cos(), self.rotation.sin());
-        self.position += direction_vec * (self.velocity + 200.0) * delta_time as f32;
-        if self.position.x < 0.0
-            || self.position.x > screen_width()
-            || self.position.y < 0.0
-            || self.position.y > screen_height()
+    /// Keeps the current position as the position of the previous tick.
+    pub fn save_previous(&mut self) {
+        self.previous_position = self.position;
+    }
+
+    /// Met à jour la position du missile, qui fait le tour du monde comme
+    /// les astéroïdes. Désactive le missile à la fin de sa durée de vie.
+    pub fn update(&mut self, delta_time: f64, world: &World) {
+        let delta_time = delta_time as f32;
+        self.position = world.wrap(self.position + self.velocity * delta_time);
+        self.age += delta_time;
+        self.traveled += self.velocity.length() * delta_time;
+        if self.age >= self.lifetime
+            || self
+                .max_distance
+                .is_some_and(|max_distance| self.traveled >= max_distance)
         {
-            self.active = false; // Désactive le missile hors de l'écran
+            self.active = false;
         }
     }
 
-    /// Affiche le missile si actif.
-    pub fn draw(&self) {
+    /// Affiche le missile si actif, interpolé entre les deux derniers ticks.
+    pub fn draw(&self, alpha: f32, world: &World) {
+        self.draw_colored(alpha, world, RED); // Dessine un petit cercle rouge
+    }
+
+    /// Affiche le missile de la couleur donnée.
+    pub fn draw_colored(&self, alpha: f32, world: &World, color: Color) {
         if self.active {
-            draw_circle(self.position.x, self.position.y, self.size, RED); // Dessine un petit cercle rouge
+            let position = world.lerp(self.previous_position, self.position, alpha);
+            for image in world.images(position, self.size) {
+                draw_circle(image.x, image.y, self.size, color);
+            }
         }
     }
 }
@@ -49,20 +100,74 @@ mod tests {
     #[test]
     fn test_missile_creation() {
         let position = vec2(100.0, 100.0);
-        let direction = 1.0;
         let rotation = 0.0;
-        let missile = Missile::new(position, direction, rotation);
+        let config = MissileConfig::default();
+        let missile = Missile::new(position, Vec2::ZERO, rotation, &config);
 
         assert_eq!(missile.position, position);
-        assert_eq!(missile.velocity, direction.abs());
+        assert_eq!(missile.velocity, vec2(config.speed, 0.0));
         assert_eq!(missile.rotation, rotation);
         assert!(missile.active);
         assert_eq!(missile.size, 5.0);
     }
 
+    #[test]
+    fn test_missile_inherits_ship_velocity() {
+        let config = MissileConfig::default();
+        let missile = Missile::new(Vec2::ZERO, vec2(0.0, 100.0), 0.0, &config);
+        assert_eq!(missile.velocity, vec2(config.speed, 100.0));
+    }
+
+    #[test]
+    fn test_missile_wraps_around_world() {
+        let world = World::new(200.0, 200.0);
+        let config = MissileConfig {
+            speed: 200.0,
+            ..MissileConfig::default()
+        };
+        let mut missile = Missile::new(vec2(190.0, 100.0), Vec2::ZERO, 0.0, &config);
+
+        // 200px/s vers la droite : le missile réapparaît à gauche, sans
+        // perdre la distance parcourue au-delà du bord
+        missile.update(0.1, &world);
+        assert!(missile.active);
+        assert_eq!(missile.position, vec2(10.0, 100.0));
+    }
+
+    #[test]
+    fn test_missile_expires() {
+        let world = World::new(200.0, 200.0);
+        let config = MissileConfig {
+            speed: 100.0,
+            lifetime: 1.0,
+            max_distance: None,
+        };
+        let mut missile = Missile::new(vec2(100.0, 100.0), Vec2::ZERO, 0.0, &config);
+        missile.update(0.5, &world);
+        assert!(missile.active);
+        missile.update(0.5, &world);
+        assert!(!missile.active);
+
+        // La distance parcourue peut aussi limiter la portée
+        let config = MissileConfig {
+            max_distance: Some(30.0),
+            ..config
+        };
+        let mut missile = Missile::new(vec2(100.0, 100.0), Vec2::ZERO, 0.0, &config);
+        missile.update(0.2, &world);
+        assert!(missile.active);
+        missile.update(0.2, &world);
+        assert!(!missile.active);
+    }
+
     #[test]
     fn test_missile_draw() {
-        let missile = Missile::new(vec2(100.0, 100.0), 1.0, 0.0);
+        let missile = Missile::new(
+            vec2(100.0, 100.0),
+            Vec2::ZERO,
+            0.0,
+            &MissileConfig::default(),
+        );
         assert!(missile.active);
     }
 }
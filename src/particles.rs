@@ -0,0 +1,241 @@
+use ::rand::rngs::SmallRng;
+use ::rand::{Rng, SeedableRng};
+use macroquad::prelude::*;
+use std::f32::consts::PI;
+
+use crate::world::World;
+
+/// How many particles are emitted and how many may live at once.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParticleConfig {
+    pub enabled: bool,
+    /// Particles alive at once, the new ones are dropped beyond.
+    pub max_particles: usize,
+    /// Debris of a destroyed asteroid for each of its size.
+    pub debris_per_size: usize,
+    /// Particles of the burst of the destroyed spaceship.
+    pub burst: usize,
+    /// Particles behind the spaceship at each tick of thrust.
+    pub exhaust: usize,
+    /// Whether the missiles leave a trail.
+    pub trails: bool,
+}
+
+impl Default for ParticleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_particles: 1500,
+            debris_per_size: 8,
+            burst: 60,
+            exhaust: 2,
+            trails: true,
+        }
+    }
+}
+
+/// A speck of debris or smoke, only drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub size: f32,
+    pub color: Color,
+    // Seconds since it was emitted, and before it fades out
+    pub age: f32,
+    pub lifetime: f32,
+    // Position at the previous tick, for the interpolation of the rendering
+    pub previous_position: Vec2,
+}
+
+/// The particles of a game, kept in a pool whose slots are reused so that
+/// emitting does not allocate once the pool has grown.
+///
+/// They have their own random generator: the effects never change the
+/// random values of the simulation, and a replay plays the same.
+pub struct Particles {
+    pub config: ParticleConfig,
+    // The live particles come first, the rest of the pool is free slots
+    pool: Vec<Particle>,
+    live: usize,
+    rng: SmallRng,
+}
+
+impl Default for Particles {
+    fn default() -> Self {
+        Self::new(ParticleConfig::default())
+    }
+}
+
+impl Particles {
+    pub fn new(config: ParticleConfig) -> Self {
+        Self {
+            config,
+            pool: Vec::new(),
+            live: 0,
+            rng: SmallRng::seed_from_u64(0),
+        }
+    }
+
+    /// The particles alive.
+    pub fn live(&self) -> &[Particle] {
+        &self.pool[..self.live]
+    }
+
+    /// Removes every particle, keeping the pool.
+    pub fn clear(&mut self) {
+        self.live = 0;
+    }
+
+    /// Adds a particle, unless there are already as many as allowed.
+    pub fn emit(&mut self, position: Vec2, velocity: Vec2, size: f32, color: Color, lifetime: f32) {
+        if !self.config.enabled || self.live >= self.config.max_particles {
+            return;
+        }
+        let particle = Particle {
+            position,
+            velocity,
+            size,
+            color,
+            age: 0.0,
+            lifetime,
+            previous_position: position,
+        };
+        if self.live < self.pool.len() {
+            self.pool[self.live] = particle;
+        } else {
+            self.pool.push(particle);
+        }
+        self.live += 1;
+    }
+
+    /// Debris flying away from a destroyed asteroid, more for a larger one.
+    pub fn explosion(&mut self, position: Vec2, velocity: Vec2, size: u8, color: Color) {
+        for _ in 0..self.config.debris_per_size * size as usize {
+            let direction = Vec2::from_angle(self.rng.gen_range(0.0..2.0 * PI));
+            let speed = self.rng.gen_range(20.0..120.0) * (1.0 + size as f32 * 0.3);
+            let lifetime = self.rng.gen_range(0.4..1.0);
+            let size = self.rng.gen_range(1.0..3.0);
+            self.emit(
+                position,
+                velocity + direction * speed,
+                size,
+                color,
+                lifetime,
+            );
+        }
+    }
+
+    /// The burst of the destroyed spaceship.
+    pub fn burst(&mut self, position: Vec2, velocity: Vec2) {
+        for index in 0..self.config.burst {
+            let direction = Vec2::from_angle(self.rng.gen_range(0.0..2.0 * PI));
+            let speed = self.rng.gen_range(40.0..260.0);
+            let color = if index % 3 == 0 { ORANGE } else { WHITE };
+            let lifetime = self.rng.gen_range(0.6..1.5);
+            self.emit(position, velocity + direction * speed, 2.0, color, lifetime);
+        }
+    }
+
+    /// Flames going out of the back of the spaceship, `heading` being the
+    /// way it faces.
+    pub fn exhaust(&mut self, position: Vec2, velocity: Vec2, heading: Vec2) {
+        for _ in 0..self.config.exhaust {
+            let spread = self.rng.gen_range(-0.35..0.35);
+            let speed = self.rng.gen_range(80.0..160.0);
+            let direction = Vec2::from_angle(spread).rotate(-heading);
+            let color = if self.rng.gen() { ORANGE } else { YELLOW };
+            let lifetime = self.rng.gen_range(0.15..0.35);
+            self.emit(position, velocity + direction * speed, 1.5, color, lifetime);
+        }
+    }
+
+    /// A still speck left behind a missile.
+    pub fn trail(&mut self, position: Vec2, color: Color) {
+        if self.config.trails {
+            self.emit(position, Vec2::ZERO, 1.5, color, 0.2);
+        }
+    }
+
+    /// Moves the particles by one step of the simulation and removes the
+    /// ones at the end of their life.
+    pub fn update(&mut self, delta_time: f64, world: &World) {
+        let delta_time = delta_time as f32;
+        let mut index = 0;
+        while index < self.live {
+            let particle = &mut self.pool[index];
+            particle.age += delta_time;
+            if particle.age >= particle.lifetime {
+                // The last live particle takes the free slot
+                self.live -= 1;
+                self.pool.swap(index, self.live);
+                continue;
+            }
+            particle.previous_position = particle.position;
+            particle.position = world.wrap(particle.position + particle.velocity * delta_time);
+            index += 1;
+        }
+    }
+
+    /// Draws the particles, fading as they age, interpolated between the
+    /// last two ticks.
+    pub fn draw(&self, alpha: f32, world: &World) {
+        for particle in self.live() {
+            let position = world.lerp(particle.previous_position, particle.position, alpha);
+            let mut color = particle.color;
+            color.a *= 1.0 - particle.age / particle.lifetime;
+            draw_circle(position.x, position.y, particle.size, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_particles_expire() {
+        let world = World::new(800.0, 600.0);
+        let mut particles = Particles::default();
+        particles.emit(vec2(795.0, 10.0), vec2(600.0, 0.0), 1.0, WHITE, 0.5);
+        particles.emit(vec2(10.0, 10.0), Vec2::ZERO, 1.0, WHITE, 0.05);
+
+        particles.update(1.0 / 60.0, &world);
+        assert_eq!(particles.live().len(), 2);
+        // Passée de l'autre côté du bord
+        assert_eq!(particles.live()[0].position, vec2(5.0, 10.0));
+
+        for _ in 0..3 {
+            particles.update(1.0 / 60.0, &world);
+        }
+        assert_eq!(particles.live().len(), 1);
+        assert_eq!(particles.live()[0].lifetime, 0.5);
+    }
+
+    #[test]
+    fn test_pool_is_capped_and_reused() {
+        let world = World::new(800.0, 600.0);
+        let config = ParticleConfig {
+            max_particles: 50,
+            ..ParticleConfig::default()
+        };
+        let mut particles = Particles::new(config);
+        particles.explosion(vec2(100.0, 100.0), Vec2::ZERO, 3, WHITE);
+        particles.burst(vec2(100.0, 100.0), Vec2::ZERO);
+        assert_eq!(particles.live().len(), 50);
+
+        // Les emplacements libérés servent aux nouvelles particules
+        particles.update(2.0, &world);
+        assert!(particles.live().is_empty());
+        particles.explosion(vec2(100.0, 100.0), Vec2::ZERO, 1, WHITE);
+        assert_eq!(particles.live().len(), config.debris_per_size);
+        assert_eq!(particles.pool.len(), 50);
+
+        let mut disabled = Particles::new(ParticleConfig {
+            enabled: false,
+            ..config
+        });
+        disabled.burst(Vec2::ZERO, Vec2::ZERO);
+        assert!(disabled.live().is_empty());
+    }
+}
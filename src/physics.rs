@@ -0,0 +1,156 @@
+use macroquad::math::Vec2;
+
+use crate::asteroid::Asteroid;
+use crate::world::World;
+
+/// How the asteroids collide with each other.
+///
+/// Disabled, the asteroids go through each other like the arcade game.
+/// Enabled, they bounce off each other with a mass growing with their area,
+/// and an impact hard enough breaks both of them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhysicsConfig {
+    pub enabled: bool,
+    /// Share of the approaching speed kept after an impact, 1 for perfectly
+    /// elastic impacts and 0 for asteroids moving on together.
+    pub restitution: f32,
+    /// Energy of an impact, in mass × (px/s)², from which both asteroids
+    /// break, `None` to only bounce.
+    pub split_energy: Option<f32>,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            restitution: 1.0,
+            split_energy: Some(15_000_000.0),
+        }
+    }
+}
+
+impl PhysicsConfig {
+    /// Pushes two touching asteroids apart and exchanges their momentum
+    /// along the line between their centers, through the edges of the
+    /// playfield if needed. Returns the energy of the impact, the kinetic
+    /// energy of their approach, which is 0 when they already move apart.
+    pub fn bounce(&self, world: &World, first: &mut Asteroid, second: &mut Asteroid) -> f32 {
+        let delta = world.delta(first.position, second.position);
+        let normal = delta.try_normalize().unwrap_or(Vec2::X);
+        let inverse_first = 1.0 / first.mass().max(f32::EPSILON);
+        let inverse_second = 1.0 / second.mass().max(f32::EPSILON);
+        let inverse_total = inverse_first + inverse_second;
+
+        // Separated in proportion to their inverse mass so they don't stay stuck
+        let overlap = first.radius() + second.radius() - delta.length();
+        if overlap > 0.0 {
+            let push = normal * overlap / inverse_total;
+            first.position = world.wrap(first.position - push * inverse_first);
+            second.position = world.wrap(second.position + push * inverse_second);
+        }
+
+        let approach = (second.velocity - first.velocity).dot(normal);
+        if approach >= 0.0 {
+            return 0.0;
+        }
+        let impulse = normal * -(1.0 + self.restitution) * approach / inverse_total;
+        first.velocity -= impulse * inverse_first;
+        second.velocity += impulse * inverse_second;
+        // Energy of the approach in the frame of their center of mass
+        0.5 * approach * approach / inverse_total
+    }
+
+    /// Whether an impact of the given energy breaks the asteroids.
+    pub fn breaks(&self, energy: f32) -> bool {
+        self.split_energy
+            .is_some_and(|split_energy| energy >= split_energy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::math::vec2;
+
+    fn asteroid(position: Vec2, velocity: Vec2, size: u8) -> Asteroid {
+        Asteroid::new(
+            Some(position),
+            Some(velocity),
+            Some(size),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn momentum(asteroids: &[&Asteroid]) -> Vec2 {
+        asteroids.iter().map(|a| a.velocity * a.mass()).sum()
+    }
+
+    fn energy(asteroids: &[&Asteroid]) -> f32 {
+        asteroids
+            .iter()
+            .map(|a| 0.5 * a.mass() * a.velocity.length_squared())
+            .sum()
+    }
+
+    #[test]
+    fn test_equal_masses_swap_velocities() {
+        let world = World::new(800.0, 600.0);
+        let config = PhysicsConfig::default();
+        let mut first = asteroid(vec2(100.0, 100.0), vec2(50.0, 0.0), 2);
+        let mut second = asteroid(vec2(170.0, 100.0), vec2(-30.0, 0.0), 2);
+
+        let impact = config.bounce(&world, &mut first, &mut second);
+        assert!((first.velocity - vec2(-30.0, 0.0)).length() < 1e-3);
+        assert!((second.velocity - vec2(50.0, 0.0)).length() < 1e-3);
+        // Poussés hors de contact
+        assert!(world.distance(first.position, second.position) >= 80.0 - 1e-3);
+        // Half of the reduced mass times the square of the approaching speed
+        assert!((impact - 0.5 * 800.0 * 80.0 * 80.0).abs() < 1.0);
+
+        // Ils s'éloignent déjà, rien ne change
+        let velocities = (first.velocity, second.velocity);
+        assert_eq!(config.bounce(&world, &mut first, &mut second), 0.0);
+        assert_eq!((first.velocity, second.velocity), velocities);
+    }
+
+    #[test]
+    fn test_bounce_conserves_momentum_and_energy() {
+        let world = World::new(800.0, 600.0);
+        let config = PhysicsConfig::default();
+        // A glancing impact through the right edge, with different masses
+        let mut first = asteroid(vec2(780.0, 300.0), vec2(120.0, 40.0), 3);
+        let mut second = asteroid(vec2(30.0, 340.0), vec2(-80.0, 10.0), 1);
+        let (before, kinetic) = (momentum(&[&first, &second]), energy(&[&first, &second]));
+
+        assert!(config.bounce(&world, &mut first, &mut second) > 0.0);
+        let after = momentum(&[&first, &second]);
+        assert!((after - before).length() < before.length() * 1e-4);
+        let lost = kinetic - energy(&[&first, &second]);
+        assert!(lost.abs() < kinetic * 1e-4);
+
+        // Sans restitution, ils repartent ensemble le long de la normale
+        let config = PhysicsConfig {
+            restitution: 0.0,
+            ..config
+        };
+        let mut first = asteroid(vec2(100.0, 100.0), vec2(60.0, 0.0), 1);
+        let mut second = asteroid(vec2(130.0, 100.0), Vec2::ZERO, 1);
+        config.bounce(&world, &mut first, &mut second);
+        assert!((first.velocity - second.velocity).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_breaks() {
+        let config = PhysicsConfig::default();
+        assert!(config.breaks(20_000_000.0));
+        assert!(!config.breaks(1_000.0));
+        let config = PhysicsConfig {
+            split_energy: None,
+            ..config
+        };
+        assert!(!config.breaks(f32::MAX));
+    }
+}
@@ -0,0 +1,484 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::input::{Input, SpeedModifier};
+use crate::world::World;
+
+// Identifies a replay file and the version of its format
+const MAGIC: &[u8; 4] = b"AREP";
+const VERSION: u8 = 3;
+// magic + version + seed + world + modes + campaign + classes + score
+// + loop number + number of ticks
+const HEADER_SIZE: usize = 4 + 1 + 8 + 4 + 4 + 1 + 4 + 8 + 16 + 16 + 8;
+// Bytes of the input of one tick
+const INPUT_SIZE: usize = 2;
+
+/// Everything that can go wrong with a replay.
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    /// The file is not a replay or is truncated.
+    Invalid(String),
+    /// The file was written by another version of the game.
+    Version(u8),
+    /// The replay was recorded with another table of asteroid classes.
+    Classes {
+        expected: u64,
+        found: u64,
+    },
+    /// The playback did not end like the recorded game.
+    Mismatch {
+        expected_score: u128,
+        score: u128,
+        expected_loop_number: u128,
+        loop_number: u128,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "Cannot access the replay: {error}"),
+            ReplayError::Invalid(reason) => write!(f, "Invalid replay: {reason}"),
+            ReplayError::Version(version) => write!(
+                f,
+                "Replay of version {version} cannot be played by version {VERSION}"
+            ),
+            ReplayError::Classes { expected, found } => write!(
+                f,
+                "Replay recorded with other asteroid classes ({expected:016x}, \
+                 the game has {found:016x}): restore the file they were read from"
+            ),
+            ReplayError::Mismatch {
+                expected_score,
+                score,
+                expected_loop_number,
+                loop_number,
+            } => write!(
+                f,
+                "Replay diverged: score {score} (recorded {expected_score}), \
+                 cycle {loop_number} (recorded {expected_loop_number})"
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        ReplayError::Io(error)
+    }
+}
+
+/// A recorded game: the seed, the playfield, the modes it was played in
+/// and the input of every tick, plus how the game ended to check the
+/// playback against it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub world: World,
+    /// Whether the asteroids had vector outlines, see `ShapeConfig`.
+    pub vector: bool,
+    /// Whether the asteroids bounced off each other, see `PhysicsConfig`.
+    pub bounce: bool,
+    /// The last level of the campaign, `None` for the endless game.
+    pub campaign: Option<u32>,
+    /// The fingerprint of the asteroid classes, see `AsteroidClasses`.
+    pub classes: u64,
+    pub inputs: Vec<Input>,
+    pub score: u128,
+    pub loop_number: u128,
+}
+
+impl Replay {
+    /// Starts an empty recording.
+    pub fn new(seed: u64, world: World) -> Self {
+        Self {
+            seed,
+            world,
+            vector: false,
+            bounce: false,
+            campaign: None,
+            classes: 0,
+            inputs: Vec::new(),
+            score: 0,
+            loop_number: 0,
+        }
+    }
+
+    /// Adds the input of one tick.
+    pub fn record(&mut self, input: Input) {
+        self.inputs.push(input);
+    }
+
+    /// Keeps how the game ended.
+    pub fn finish(&mut self, score: u128, loop_number: u128) {
+        self.score = score;
+        self.loop_number = loop_number;
+    }
+
+    /// Checks that a playback ended like the recorded game.
+    pub fn verify(&self, score: u128, loop_number: u128) -> Result<(), ReplayError> {
+        if score == self.score && loop_number == self.loop_number {
+            Ok(())
+        } else {
+            Err(ReplayError::Mismatch {
+                expected_score: self.score,
+                score,
+                expected_loop_number: self.loop_number,
+                loop_number,
+            })
+        }
+    }
+
+    /// Encodes the replay: a fixed header then two bytes per tick.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + INPUT_SIZE * self.inputs.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.world.width.to_le_bytes());
+        bytes.extend_from_slice(&self.world.height.to_le_bytes());
+        bytes.push(self.vector as u8 | (self.bounce as u8) << 1);
+        // There is no level 0, it stands for the endless game
+        bytes.extend_from_slice(&self.campaign.unwrap_or(0).to_le_bytes());
+        bytes.extend_from_slice(&self.classes.to_le_bytes());
+        bytes.extend_from_slice(&self.score.to_le_bytes());
+        bytes.extend_from_slice(&self.loop_number.to_le_bytes());
+        bytes.extend_from_slice(&(self.inputs.len() as u64).to_le_bytes());
+        for input in &self.inputs {
+            bytes.extend_from_slice(&encode_input(*input).to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes a replay written by [`Replay::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        if bytes.len() < 5 || &bytes[..4] != MAGIC {
+            return Err(ReplayError::Invalid("not a replay file".to_string()));
+        }
+        if bytes[4] != VERSION {
+            return Err(ReplayError::Version(bytes[4]));
+        }
+        if bytes.len() < HEADER_SIZE {
+            return Err(ReplayError::Invalid("truncated header".to_string()));
+        }
+
+        let mut reader = Reader { bytes, offset: 5 };
+        let seed = u64::from_le_bytes(reader.take());
+        let width = f32::from_le_bytes(reader.take());
+        let height = f32::from_le_bytes(reader.take());
+        let world = World::new(width, height);
+        if !world.is_valid() {
+            return Err(ReplayError::Invalid(format!(
+                "invalid playfield of {width}x{height}"
+            )));
+        }
+        let [modes] = reader.take();
+        if modes >> 2 != 0 {
+            return Err(ReplayError::Invalid(format!("unknown modes {modes:#010b}")));
+        }
+        let campaign = Some(u32::from_le_bytes(reader.take())).filter(|last| *last > 0);
+        let classes = u64::from_le_bytes(reader.take());
+        let score = u128::from_le_bytes(reader.take());
+        let loop_number = u128::from_le_bytes(reader.take());
+        let ticks = u64::from_le_bytes(reader.take()) as usize;
+
+        let body = &bytes[HEADER_SIZE..];
+        if body.len() != ticks.saturating_mul(INPUT_SIZE) {
+            return Err(ReplayError::Invalid(format!(
+                "expected {ticks} ticks, found {} bytes",
+                body.len()
+            )));
+        }
+        let inputs = body
+            .chunks_exact(INPUT_SIZE)
+            .map(|chunk| decode_input(u16::from_le_bytes([chunk[0], chunk[1]])))
+            .collect::<Result<Vec<Input>, ReplayError>>()?;
+
+        Ok(Self {
+            seed,
+            world,
+            vector: modes & 1 != 0,
+            bounce: modes & 1 << 1 != 0,
+            campaign,
+            classes,
+            inputs,
+            score,
+            loop_number,
+        })
+    }
+
+    /// Writes the replay to a file.
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Reads a replay from a file.
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+/// Feeds the inputs of a replay back to the simulation, one per tick.
+#[derive(Clone, Debug)]
+pub struct Playback {
+    pub replay: Replay,
+    pub tick: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, tick: 0 }
+    }
+
+    /// Returns the input of the next tick, or `None` once the replay is over.
+    pub fn next_input(&mut self) -> Option<Input> {
+        let input = self.replay.inputs.get(self.tick).copied();
+        self.tick += 1;
+        input
+    }
+
+    /// The speed the recorded player was playing at for the next tick.
+    pub fn speed(&self) -> SpeedModifier {
+        self.replay
+            .inputs
+            .get(self.tick)
+            .map_or(SpeedModifier::Normal, |input| input.speed)
+    }
+
+    /// Returns whether every recorded input has been played.
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.replay.inputs.len()
+    }
+}
+
+/// Reads the fixed size fields of the header.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let mut field = [0; N];
+        field.copy_from_slice(&self.bytes[self.offset..self.offset + N]);
+        self.offset += N;
+        field
+    }
+}
+
+// One bit per action, bits 6 and 7 for the speed, then the actions added later
+fn encode_input(input: Input) -> u16 {
+    let speed = match input.speed {
+        SpeedModifier::Normal => 0,
+        SpeedModifier::Slow => 1,
+        SpeedModifier::Fast => 2,
+    };
+    input.thrust as u16
+        | (input.reverse as u16) << 1
+        | (input.rotate_left as u16) << 2
+        | (input.rotate_right as u16) << 3
+        | (input.fire as u16) << 4
+        | (input.stop as u16) << 5
+        | speed << 6
+        | (input.hyperspace as u16) << 8
+}
+
+fn decode_input(bits: u16) -> Result<Input, ReplayError> {
+    if bits >> 9 != 0 {
+        return Err(ReplayError::Invalid(format!("unknown input {bits:#018b}")));
+    }
+    let speed = match bits >> 6 & 0b11 {
+        0 => SpeedModifier::Normal,
+        1 => SpeedModifier::Slow,
+        2 => SpeedModifier::Fast,
+        _ => {
+            return Err(ReplayError::Invalid(format!(
+                "unknown speed in {bits:#018b}"
+            )))
+        }
+    };
+    Ok(Input {
+        thrust: bits & 1 != 0,
+        reverse: bits & 1 << 1 != 0,
+        rotate_left: bits & 1 << 2 != 0,
+        rotate_right: bits & 1 << 3 != 0,
+        fire: bits & 1 << 4 != 0,
+        stop: bits & 1 << 5 != 0,
+        hyperspace: bits & 1 << 8 != 0,
+        speed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::{Gamestate, TICKS};
+
+    fn sample_replay() -> Replay {
+        let mut replay = Replay::new(42, World::new(800.0, 600.0));
+        replay.vector = true;
+        replay.bounce = true;
+        replay.campaign = Some(8);
+        replay.classes = 0x0123_4567_89ab_cdef;
+        replay.record(Input {
+            thrust: true,
+            fire: true,
+            ..Input::default()
+        });
+        replay.record(Input {
+            rotate_right: true,
+            hyperspace: true,
+            speed: SpeedModifier::Fast,
+            ..Input::default()
+        });
+        replay.finish(300, 2);
+        replay
+    }
+
+    #[test]
+    fn test_replay_round_trip() {
+        let replay = sample_replay();
+        let bytes = replay.to_bytes();
+
+        assert_eq!(bytes.len(), HEADER_SIZE + 2 * INPUT_SIZE);
+        assert_eq!(Replay::from_bytes(&bytes).unwrap(), replay);
+    }
+
+    #[test]
+    fn test_replay_invalid_files() {
+        assert!(matches!(
+            Replay::from_bytes(b"not a replay"),
+            Err(ReplayError::Invalid(_))
+        ));
+
+        let mut bytes = sample_replay().to_bytes();
+        bytes.pop();
+        assert!(matches!(
+            Replay::from_bytes(&bytes),
+            Err(ReplayError::Invalid(_))
+        ));
+
+        // The playfield must have a size, or the random positions panic
+        for (width, height) in [(0.0, 600.0), (800.0, f32::NAN)] {
+            let mut replay = sample_replay();
+            replay.world = World::new(width, height);
+            assert!(matches!(
+                Replay::from_bytes(&replay.to_bytes()),
+                Err(ReplayError::Invalid(_))
+            ));
+        }
+
+        let mut modes = sample_replay().to_bytes();
+        modes[4 + 1 + 8 + 4 + 4] = 0xff;
+        assert!(matches!(
+            Replay::from_bytes(&modes),
+            Err(ReplayError::Invalid(_))
+        ));
+
+        bytes[4] = VERSION + 1;
+        assert!(matches!(
+            Replay::from_bytes(&bytes),
+            Err(ReplayError::Version(_))
+        ));
+    }
+
+    #[test]
+    fn test_playback_matches_recording() {
+        let world = World::new(800.0, 600.0);
+        let mut gamestate = Gamestate::new(world, 3);
+        gamestate.new_game();
+
+        // Turn and fire for a while, at an irregular frame rate
+        for frame in 0..400 {
+            gamestate.input.rotate_left = frame % 90 < 45;
+            gamestate.input.thrust = frame % 120 < 20;
+            gamestate.input.fire = frame % 7 == 0;
+            gamestate.update(TICKS * (1.0 + (frame % 3) as f64 / 2.0));
+        }
+        gamestate.game_started = false;
+        gamestate.end_replay();
+        let replay = gamestate.finished_replay.take().unwrap();
+        assert!(replay.score > 0);
+
+        // Decoding keeps everything needed to replay
+        let replay = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        let mut playback = Gamestate::new(World::new(1920.0, 1080.0), 0);
+        playback.start_playback(replay.clone()).unwrap();
+        while playback.playback.is_some() {
+            playback.update(TICKS);
+        }
+
+        assert!(matches!(playback.playback_result, Some(Ok(()))));
+        assert_eq!(playback.score, replay.score);
+        assert_eq!(playback.loop_number, replay.loop_number);
+    }
+
+    #[test]
+    fn test_playback_keeps_recorded_modes() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 5);
+        gamestate.shape_config.enabled = true;
+        gamestate.physics_config.enabled = true;
+        gamestate.level_config.campaign = Some(1);
+        gamestate.new_game();
+        for frame in 0..300 {
+            gamestate.input.fire = frame % 5 == 0;
+            gamestate.input.rotate_right = frame % 60 < 30;
+            gamestate.update(TICKS);
+        }
+        gamestate.game_started = false;
+        gamestate.end_replay();
+        let replay = gamestate.finished_replay.take().unwrap();
+        assert!(replay.vector && replay.bounce);
+        assert_eq!(replay.campaign, Some(1));
+
+        // Launched without the options, the playback still uses the outlines,
+        // the bounces and the campaign
+        let mut playback = Gamestate::new(World::new(800.0, 600.0), 0);
+        playback
+            .start_playback(Replay::from_bytes(&replay.to_bytes()).unwrap())
+            .unwrap();
+        assert!(playback.shape_config.enabled && playback.physics_config.enabled);
+        assert_eq!(playback.level_config.campaign, Some(1));
+        while playback.playback.is_some() {
+            playback.update(TICKS);
+        }
+        assert!(matches!(playback.playback_result, Some(Ok(()))));
+    }
+
+    #[test]
+    fn test_playback_needs_recorded_classes() {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 5);
+        gamestate.new_game();
+        gamestate.update(TICKS);
+        gamestate.end_replay();
+        let replay = gamestate.finished_replay.take().unwrap();
+
+        // The table was edited since the recording
+        let mut playback = Gamestate::new(World::new(800.0, 600.0), 0);
+        let mut edited = replay.clone();
+        edited.classes ^= 1;
+        let error = playback.start_playback(edited).unwrap_err();
+        assert!(matches!(error, ReplayError::Classes { .. }));
+        assert!(error.to_string().contains("asteroid classes"));
+        assert!(!playback.game_started);
+
+        assert!(playback.start_playback(replay).is_ok());
+    }
+
+    #[test]
+    fn test_verify_mismatch() {
+        let replay = sample_replay();
+        assert!(replay.verify(300, 2).is_ok());
+        assert!(matches!(
+            replay.verify(200, 2),
+            Err(ReplayError::Mismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,593 @@
+use ::rand::rngs::SmallRng;
+use ::rand::{Rng, SeedableRng};
+use macroquad::prelude::*;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::{FromStr, SplitWhitespace};
+
+use crate::asteroid::{Asteroid, TEXTURES};
+use crate::general::Gamestate;
+use crate::missile::{Missile, MissileConfig};
+use crate::spaceship::Spaceship;
+use crate::ufo::{Ufo, UfoKind};
+use crate::weapon::Weapon;
+use crate::world::World;
+
+// First word of a save file, followed by the version of the format
+const HEADER: &str = "asteroids-save";
+pub const VERSION: u32 = 14;
+
+/// Everything that can go wrong with a save.
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    /// The save was written with another version of the format.
+    Version {
+        found: u32,
+        game: String,
+    },
+    /// The file is not a save or is damaged.
+    Invalid {
+        line: usize,
+        reason: String,
+    },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "Cannot access the save: {error}"),
+            SaveError::Version { found, game } => write!(
+                f,
+                "The save was made by version {game} of the game (format {found}), \
+                 this version only reads format {VERSION}"
+            ),
+            SaveError::Invalid { line, reason } => {
+                write!(f, "The save is damaged at line {line}: {reason}")
+            }
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+/// Writes the game in progress to a file.
+pub fn save_game(gamestate: &mut Gamestate, path: &Path) -> Result<(), SaveError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, encode(gamestate))?;
+    Ok(())
+}
+
+/// Replaces the game of the state by the one saved in a file.
+pub fn load_game(gamestate: &mut Gamestate, path: &Path) -> Result<(), SaveError> {
+    decode(gamestate, &fs::read_to_string(path)?)
+}
+
+/// Encodes the game in progress, one object per line.
+///
+/// The generator cannot give its internal state, so it is reseeded from a
+/// value drawn from itself and that value is saved: the game goes on with
+/// the same random values whether it was reloaded or not.
+pub fn encode(gamestate: &mut Gamestate) -> String {
+    let checkpoint: u64 = gamestate.rng.gen();
+    gamestate.rng = SmallRng::seed_from_u64(checkpoint);
+
+    let mut lines = vec![
+        format!("{HEADER} {VERSION} {}", env!("CARGO_PKG_VERSION")),
+        format!("seed {}", gamestate.seed),
+        format!("rng {checkpoint}"),
+        format!("world {} {}", gamestate.world.width, gamestate.world.height),
+        match gamestate.level_config.campaign {
+            Some(last) => format!("campaign {last}"),
+            None => "campaign none".to_string(),
+        },
+        format!(
+            "modes {} {}",
+            gamestate.shape_config.enabled, gamestate.physics_config.enabled
+        ),
+        format!("lives {}", gamestate.lives),
+        format!(
+            "extra_life {} {}",
+            gamestate.next_extra_life, gamestate.extra_life_notice
+        ),
+        format!("score {}", gamestate.score),
+        format!("loop_number {}", gamestate.loop_number),
+        format!("level {} {}", gamestate.level, gamestate.intermission),
+    ];
+    // The spaceship may be waiting for a safe place to come back
+    if let Some(waited) = gamestate.respawn_wait {
+        lines.push(format!("respawn {waited}"));
+    }
+
+    let spaceship = &gamestate.spaceship;
+    lines.push(format!(
+        "spaceship {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+        spaceship.position.x,
+        spaceship.position.y,
+        spaceship.velocity.x,
+        spaceship.velocity.y,
+        spaceship.max_speed,
+        spaceship.acceleration,
+        spaceship.drag,
+        spaceship.rotation,
+        spaceship.turn_rate,
+        spaceship.size,
+        spaceship.shield,
+        spaceship.invulnerability,
+        spaceship.time_since_hit,
+        spaceship.hyperspace_cooldown,
+        spaceship.materialization,
+    ));
+    let weapon = &spaceship.weapon;
+    lines.push(format!(
+        "weapon {} {} {} {} {} {} {} {}",
+        weapon.cooldown,
+        weapon.max_missiles,
+        weapon.overheat,
+        weapon.heat_per_shot,
+        weapon.cooling_rate,
+        weapon.reload,
+        weapon.heat,
+        weapon.overheated,
+    ));
+    for asteroid in &gamestate.asteroids {
+        // The outline is its number of vertices then their coordinates,
+        // the texture comes last as it is a path
+        let mut outline = asteroid.shape.len().to_string();
+        for point in &asteroid.shape {
+            outline += &format!(" {} {}", point.x, point.y);
+        }
+        lines.push(format!(
+            "asteroid {} {} {} {} {} {} {} {} {} {} {}",
+            asteroid.position.x,
+            asteroid.position.y,
+            asteroid.velocity.x,
+            asteroid.velocity.y,
+            asteroid.size,
+            asteroid.scale,
+            asteroid.rotation,
+            asteroid.turn_rate,
+            asteroid.hit_points,
+            outline,
+            asteroid.texture,
+        ));
+    }
+    for missile in &gamestate.missiles {
+        lines.push(encode_missile("missile", missile));
+    }
+    lines.push(format!("ufo_timer {}", gamestate.ufo_timer));
+    for ufo in &gamestate.ufos {
+        let kind = match ufo.kind {
+            UfoKind::Large => "large",
+            UfoKind::Small => "small",
+        };
+        lines.push(format!(
+            "ufo {kind} {} {} {} {} {} {} {} {}",
+            ufo.position.x,
+            ufo.position.y,
+            ufo.velocity.x,
+            ufo.velocity.y,
+            ufo.active,
+            ufo.zigzag,
+            ufo.reload,
+            ufo.traveled,
+        ));
+    }
+    for missile in &gamestate.ufo_missiles {
+        lines.push(encode_missile("ufo_missile", missile));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Replaces the game of the state by the encoded one.
+/// The state is left untouched when the save cannot be read.
+pub fn decode(gamestate: &mut Gamestate, text: &str) -> Result<(), SaveError> {
+    let mut lines = text.lines().enumerate();
+
+    // Check the version before anything else
+    let header = lines.next().map_or("", |(_, line)| line);
+    let mut fields = header.split_whitespace();
+    if fields.next() != Some(HEADER) {
+        return Err(invalid(1, "not a save file"));
+    }
+    let found: u32 = field(&mut fields, 1)?;
+    if found != VERSION {
+        return Err(SaveError::Version {
+            found,
+            game: fields.next().unwrap_or("unknown").to_string(),
+        });
+    }
+
+    let mut seed = None;
+    let mut rng = None;
+    let mut world = None;
+    let mut campaign = None;
+    let mut modes = None;
+    let mut lives = None;
+    let mut extra_life = None;
+    let mut score = None;
+    let mut loop_number = None;
+    let mut level = None;
+    let mut respawn_wait = None;
+    let mut spaceship = None;
+    let mut weapon = None;
+    let mut asteroids = Vec::new();
+    let mut missiles = Vec::new();
+    let mut ufo_timer = None;
+    let mut ufos = Vec::new();
+    let mut ufo_missiles = Vec::new();
+
+    for (index, line) in lines {
+        let number = index + 1;
+        let mut fields = line.split_whitespace();
+        let Some(key) = fields.next() else {
+            continue;
+        };
+        match key {
+            "seed" => seed = Some(field::<u64>(&mut fields, number)?),
+            "rng" => rng = Some(field::<u64>(&mut fields, number)?),
+            "world" => {
+                let playfield =
+                    World::new(field(&mut fields, number)?, field(&mut fields, number)?);
+                if !playfield.is_valid() {
+                    return Err(invalid(number, "the playfield has no size"));
+                }
+                world = Some(playfield);
+            }
+            "campaign" => {
+                campaign = Some(match fields.clone().next() {
+                    Some("none") => None,
+                    _ => Some(field::<u32>(&mut fields, number)?),
+                })
+            }
+            "modes" => {
+                modes = Some((
+                    field::<bool>(&mut fields, number)?,
+                    field::<bool>(&mut fields, number)?,
+                ))
+            }
+            "lives" => lives = Some(field::<u8>(&mut fields, number)?),
+            "extra_life" => {
+                extra_life = Some((
+                    field::<u128>(&mut fields, number)?,
+                    field::<f32>(&mut fields, number)?,
+                ))
+            }
+            "score" => score = Some(field::<u128>(&mut fields, number)?),
+            "loop_number" => loop_number = Some(field::<u128>(&mut fields, number)?),
+            "respawn" => respawn_wait = Some(field::<f32>(&mut fields, number)?),
+            "level" => {
+                level = Some((
+                    field::<u32>(&mut fields, number)?,
+                    field::<f32>(&mut fields, number)?,
+                ))
+            }
+            "spaceship" => {
+                let world = world.ok_or_else(|| invalid(number, "spaceship before world"))?;
+                let mut ship = Spaceship::new(&world);
+                ship.position = vec2(field(&mut fields, number)?, field(&mut fields, number)?);
+                ship.velocity = vec2(field(&mut fields, number)?, field(&mut fields, number)?);
+                ship.max_speed = field(&mut fields, number)?;
+                ship.acceleration = field(&mut fields, number)?;
+                ship.drag = field(&mut fields, number)?;
+                ship.rotation = field(&mut fields, number)?;
+                ship.turn_rate = field(&mut fields, number)?;
+                ship.size = field(&mut fields, number)?;
+                ship.shield = field(&mut fields, number)?;
+                ship.invulnerability = field(&mut fields, number)?;
+                ship.time_since_hit = field(&mut fields, number)?;
+                ship.hyperspace_cooldown = field(&mut fields, number)?;
+                ship.materialization = field(&mut fields, number)?;
+                ship.save_previous();
+                spaceship = Some(ship);
+            }
+            "weapon" => {
+                weapon = Some(Weapon {
+                    cooldown: field(&mut fields, number)?,
+                    max_missiles: field(&mut fields, number)?,
+                    overheat: field(&mut fields, number)?,
+                    heat_per_shot: field(&mut fields, number)?,
+                    cooling_rate: field(&mut fields, number)?,
+                    reload: field(&mut fields, number)?,
+                    heat: field(&mut fields, number)?,
+                    overheated: field(&mut fields, number)?,
+                })
+            }
+            "asteroid" => {
+                let position = vec2(field(&mut fields, number)?, field(&mut fields, number)?);
+                let velocity = vec2(field(&mut fields, number)?, field(&mut fields, number)?);
+                let size = field(&mut fields, number)?;
+                let scale = field(&mut fields, number)?;
+                let rotation = field(&mut fields, number)?;
+                let turn_rate = field(&mut fields, number)?;
+                let hit_points = field(&mut fields, number)?;
+                let vertices: usize = field(&mut fields, number)?;
+                let mut shape = Vec::new();
+                for _ in 0..vertices {
+                    shape.push(vec2(
+                        field(&mut fields, number)?,
+                        field(&mut fields, number)?,
+                    ));
+                }
+                let texture = fields.collect::<Vec<&str>>().join(" ");
+                if !TEXTURES.contains(&texture) {
+                    return Err(invalid(number, &format!("unknown texture {texture}")));
+                }
+                let mut asteroid = Asteroid::new(
+                    Some(position),
+                    Some(velocity),
+                    Some(size),
+                    Some(scale),
+                    Some(rotation),
+                    Some(turn_rate),
+                    Some(texture),
+                );
+                asteroid.hit_points = hit_points;
+                asteroid.shape = shape;
+                asteroids.push(asteroid);
+            }
+            "missile" => missiles.push(decode_missile(
+                &mut fields,
+                number,
+                &gamestate.missile_config,
+            )?),
+            "ufo_timer" => ufo_timer = Some(field::<f32>(&mut fields, number)?),
+            "ufo" => {
+                let kind = match fields.next() {
+                    Some("large") => UfoKind::Large,
+                    Some("small") => UfoKind::Small,
+                    _ => return Err(invalid(number, "unknown kind of saucer")),
+                };
+                let position = vec2(field(&mut fields, number)?, field(&mut fields, number)?);
+                let mut ufo = Ufo::new(kind, position, 1.0, &gamestate.ufo_config);
+                ufo.velocity = vec2(field(&mut fields, number)?, field(&mut fields, number)?);
+                ufo.active = field(&mut fields, number)?;
+                ufo.zigzag = field(&mut fields, number)?;
+                ufo.reload = field(&mut fields, number)?;
+                ufo.traveled = field(&mut fields, number)?;
+                ufos.push(ufo);
+            }
+            "ufo_missile" => ufo_missiles.push(decode_missile(
+                &mut fields,
+                number,
+                &gamestate.ufo_config.missile,
+            )?),
+            _ => return Err(invalid(number, &format!("unknown entry {key}"))),
+        }
+    }
+
+    let missing = |name: &str| invalid(text.lines().count(), &format!("missing {name}"));
+    let seed = seed.ok_or_else(|| missing("seed"))?;
+    let rng = rng.ok_or_else(|| missing("rng"))?;
+    let world = world.ok_or_else(|| missing("world"))?;
+    let campaign = campaign.ok_or_else(|| missing("campaign"))?;
+    let (vector, bounce) = modes.ok_or_else(|| missing("modes"))?;
+    let lives = lives.ok_or_else(|| missing("lives"))?;
+    let (next_extra_life, extra_life_notice) = extra_life.ok_or_else(|| missing("extra_life"))?;
+    let score = score.ok_or_else(|| missing("score"))?;
+    let loop_number = loop_number.ok_or_else(|| missing("loop_number"))?;
+    let (level, intermission) = level.ok_or_else(|| missing("level"))?;
+    let mut spaceship = spaceship.ok_or_else(|| missing("spaceship"))?;
+    let ufo_timer = ufo_timer.ok_or_else(|| missing("ufo_timer"))?;
+    spaceship.weapon = weapon.ok_or_else(|| missing("weapon"))?;
+
+    gamestate.seed = seed;
+    gamestate.rng = SmallRng::seed_from_u64(rng);
+    gamestate.world = world;
+    gamestate.level_config.campaign = campaign;
+    gamestate.shape_config.enabled = vector;
+    gamestate.physics_config.enabled = bounce;
+    gamestate.lives = lives;
+    gamestate.next_extra_life = next_extra_life;
+    gamestate.extra_life_notice = extra_life_notice;
+    gamestate.score = score;
+    gamestate.loop_number = loop_number;
+    gamestate.level = level;
+    gamestate.wave_size = gamestate.level_config.wave(level).count;
+    gamestate.intermission = intermission;
+    gamestate.respawn_wait = respawn_wait;
+    gamestate.spaceship = spaceship;
+    gamestate.number_of_asteroids = asteroids.len();
+    gamestate.asteroids = asteroids;
+    gamestate.missiles = missiles;
+    gamestate.ufo_timer = ufo_timer;
+    gamestate.ufos = ufos;
+    gamestate.ufo_missiles = ufo_missiles;
+
+    // A resumed game cannot be replayed from its seed
+    gamestate.recording = None;
+    gamestate.playback = None;
+    gamestate.accumulator = 0.0;
+    gamestate.input = Default::default();
+    gamestate.game_started = true;
+    gamestate.game_over = false;
+    gamestate.game_won = false;
+    Ok(())
+}
+
+fn encode_missile(key: &str, missile: &Missile) -> String {
+    format!(
+        "{key} {} {} {} {} {} {} {} {} {}",
+        missile.position.x,
+        missile.position.y,
+        missile.velocity.x,
+        missile.velocity.y,
+        missile.rotation,
+        missile.active,
+        missile.size,
+        missile.age,
+        missile.traveled,
+    )
+}
+
+// The lifetime comes from the rules of the running game
+fn decode_missile(
+    fields: &mut SplitWhitespace,
+    number: usize,
+    config: &MissileConfig,
+) -> Result<Missile, SaveError> {
+    let position = vec2(field(fields, number)?, field(fields, number)?);
+    let velocity = vec2(field(fields, number)?, field(fields, number)?);
+    let rotation = field(fields, number)?;
+    let mut missile = Missile::new(position, Vec2::ZERO, rotation, config);
+    missile.velocity = velocity;
+    missile.active = field(fields, number)?;
+    missile.size = field(fields, number)?;
+    missile.age = field(fields, number)?;
+    missile.traveled = field(fields, number)?;
+    Ok(missile)
+}
+
+fn invalid(line: usize, reason: &str) -> SaveError {
+    SaveError::Invalid {
+        line,
+        reason: reason.to_string(),
+    }
+}
+
+/// Reads the next value of a line.
+fn field<T: FromStr>(fields: &mut SplitWhitespace, line: usize) -> Result<T, SaveError> {
+    let value = fields
+        .next()
+        .ok_or_else(|| invalid(line, "missing value"))?;
+    value
+        .parse()
+        .map_err(|_| invalid(line, &format!("invalid value {value}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::TICKS;
+
+    fn game_in_progress() -> Gamestate {
+        let mut gamestate = Gamestate::new(World::new(800.0, 600.0), 11);
+        // The outlines of the asteroids are saved too
+        gamestate.shape_config.enabled = true;
+        gamestate.physics_config.enabled = true;
+        gamestate.level_config.campaign = Some(20);
+        gamestate.new_game();
+        // A flying saucer comes during the game
+        gamestate.ufo_timer = 1.0;
+        gamestate.input.fire = true;
+        gamestate.input.thrust = true;
+        for _ in 0..90 {
+            gamestate.update(TICKS);
+        }
+        gamestate
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let mut gamestate = game_in_progress();
+        let text = encode(&mut gamestate);
+
+        let mut loaded = Gamestate::new(World::new(1.0, 1.0), 0);
+        decode(&mut loaded, &text).unwrap();
+
+        assert!(loaded.game_started);
+        assert_eq!(loaded.world, gamestate.world);
+        assert_eq!(loaded.seed, gamestate.seed);
+        assert_eq!(loaded.level_config.campaign, Some(20));
+        assert!(loaded.shape_config.enabled && loaded.physics_config.enabled);
+        assert_eq!(loaded.score, gamestate.score);
+        assert_eq!(loaded.loop_number, gamestate.loop_number);
+        assert_eq!(loaded.spaceship.position, gamestate.spaceship.position);
+        assert_eq!(loaded.spaceship.shield, gamestate.spaceship.shield);
+        assert_eq!(loaded.asteroids.len(), gamestate.asteroids.len());
+        assert_eq!(loaded.missiles.len(), gamestate.missiles.len());
+        assert_eq!(loaded.ufos.len(), gamestate.ufos.len());
+        // Saving again gives the same file but for the reseeded generator
+        let skip_rng = |text: &str| {
+            text.lines()
+                .filter(|line| !line.starts_with("rng"))
+                .collect::<Vec<&str>>()
+                .join("\n")
+        };
+        assert_eq!(skip_rng(&encode(&mut loaded)), skip_rng(&text));
+
+        // The endless game is saved too
+        loaded.level_config.campaign = None;
+        let endless = encode(&mut loaded);
+        decode(&mut gamestate, &endless).unwrap();
+        assert_eq!(gamestate.level_config.campaign, None);
+    }
+
+    #[test]
+    fn test_resumed_game_goes_on_identically() {
+        let mut gamestate = game_in_progress();
+        let text = encode(&mut gamestate);
+        let mut loaded = Gamestate::new(World::new(1.0, 1.0), 0);
+        decode(&mut loaded, &text).unwrap();
+        gamestate.input = Default::default();
+        gamestate.accumulator = 0.0;
+
+        for _ in 0..300 {
+            gamestate.input.fire = true;
+            loaded.input.fire = true;
+            gamestate.update(TICKS);
+            loaded.update(TICKS);
+        }
+
+        assert_eq!(loaded.score, gamestate.score);
+        assert_eq!(loaded.asteroids.len(), gamestate.asteroids.len());
+        for (a, b) in loaded.asteroids.iter().zip(&gamestate.asteroids) {
+            assert_eq!(a.position, b.position);
+        }
+    }
+
+    #[test]
+    fn test_incompatible_version() {
+        let mut gamestate = game_in_progress();
+        let text = encode(&mut gamestate).replacen(
+            &format!("{HEADER} {VERSION}"),
+            &format!("{HEADER} {}", VERSION + 1),
+            1,
+        );
+        let mut loaded = Gamestate::new(World::new(1.0, 1.0), 0);
+
+        let error = decode(&mut loaded, &text).unwrap_err();
+        assert!(matches!(error, SaveError::Version { .. }));
+        assert!(error.to_string().contains("format"));
+        // Nothing was loaded
+        assert!(!loaded.game_started);
+    }
+
+    #[test]
+    fn test_damaged_save() {
+        let mut loaded = Gamestate::new(World::new(1.0, 1.0), 0);
+        assert!(matches!(
+            decode(&mut loaded, "hello"),
+            Err(SaveError::Invalid { line: 1, .. })
+        ));
+        assert!(matches!(
+            decode(&mut loaded, &format!("{HEADER} {VERSION}\nseed abc\n")),
+            Err(SaveError::Invalid { line: 2, .. })
+        ));
+        assert!(matches!(
+            decode(&mut loaded, &format!("{HEADER} {VERSION}\nseed 1\n")),
+            Err(SaveError::Invalid { .. })
+        ));
+
+        // Un terrain sans taille ferait paniquer les positions au hasard
+        let mut gamestate = game_in_progress();
+        let text = encode(&mut gamestate);
+        for world in ["world 0 600", "world 800 NaN", "world -800 600"] {
+            let damaged = text.replacen("world 800 600", world, 1);
+            assert!(matches!(
+                decode(&mut loaded, &damaged),
+                Err(SaveError::Invalid { line: 4, .. })
+            ));
+        }
+        assert!(!loaded.game_started);
+    }
+}
@@ -0,0 +1,261 @@
+use ::rand::rngs::SmallRng;
+use ::rand::Rng;
+use macroquad::math::Vec2;
+use std::f32::consts::PI;
+
+/// How the outlines of the asteroids are generated.
+///
+/// Disabled, the asteroids are round and drawn with their texture. Enabled,
+/// every asteroid of a wave gets a jagged outline drawn as vector lines
+/// like the arcade game, and the collisions follow that outline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapeConfig {
+    pub enabled: bool,
+    /// Range of the number of vertices of a new outline.
+    pub min_vertices: usize,
+    pub max_vertices: usize,
+    /// How deep the notches go, as a fraction of the radius.
+    pub jaggedness: f32,
+}
+
+impl Default for ShapeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_vertices: 9,
+            max_vertices: 14,
+            jaggedness: 0.4,
+        }
+    }
+}
+
+impl ShapeConfig {
+    /// A random outline around the origin, its vertices in counterclockwise
+    /// order and at most at a distance of 1.
+    pub fn random_outline(&self, rng: &mut SmallRng) -> Vec<Vec2> {
+        let count = rng.gen_range(self.min_vertices.max(3)..=self.max_vertices.max(3));
+        let step = 2.0 * PI / count as f32;
+        let outline: Vec<Vec2> = (0..count)
+            .map(|index| {
+                let angle = step * (index as f32 + rng.gen_range(-0.3..=0.3));
+                let radius = rng.gen_range(1.0 - self.jaggedness.clamp(0.0, 0.9)..=1.0);
+                Vec2::from_angle(angle) * radius
+            })
+            .collect();
+        normalize(outline)
+    }
+}
+
+/// The piece of an outline inside the wedge between the angles `start` and
+/// `end` from the origin, at most half a turn. Returns its centroid in the
+/// frame of the outline and the piece centered on it and scaled back to 1.
+pub fn fragment(outline: &[Vec2], start: f32, end: f32) -> (Vec2, Vec<Vec2>) {
+    // Sutherland–Hodgman against the two half-planes of the wedge
+    let mut piece = clip(outline, Vec2::from_angle(start).perp());
+    piece = clip(&piece, -Vec2::from_angle(end).perp());
+    if piece.len() < 3 {
+        return (Vec2::ZERO, outline.to_vec());
+    }
+    let center = centroid(&piece);
+    let piece = piece.into_iter().map(|point| point - center).collect();
+    (center, normalize(piece))
+}
+
+/// The vertices of an outline at a position, turned by `rotation` and
+/// scaled by `radius`.
+pub fn place(outline: &[Vec2], position: Vec2, rotation: f32, radius: f32) -> Vec<Vec2> {
+    let rotation = Vec2::from_angle(rotation);
+    outline
+        .iter()
+        .map(|point| position + rotation.rotate(*point * radius))
+        .collect()
+}
+
+/// Returns whether a point is inside a polygon, by the even-odd rule.
+pub fn contains(polygon: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for (a, b) in edges(polygon) {
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Returns whether a circle touches a polygon.
+pub fn circle_intersects(polygon: &[Vec2], center: Vec2, radius: f32) -> bool {
+    contains(polygon, center)
+        || edges(polygon).any(|(a, b)| distance_to_segment(center, a, b) < radius)
+}
+
+/// Returns whether a circle moving in a straight line from `start` to `end`
+/// touches a polygon on its way.
+pub fn capsule_intersects(polygon: &[Vec2], start: Vec2, end: Vec2, radius: f32) -> bool {
+    contains(polygon, start)
+        || edges(polygon).any(|(a, b)| distance_between_segments(a, b, start, end) < radius)
+}
+
+/// Returns whether two polygons touch.
+pub fn polygons_intersect(first: &[Vec2], second: &[Vec2]) -> bool {
+    edges(first).any(|(a, b)| edges(second).any(|(c, d)| segments_intersect(a, b, c, d)))
+        || second.first().is_some_and(|point| contains(first, *point))
+        || first.first().is_some_and(|point| contains(second, *point))
+}
+
+/// The distance from a point to the segment between `a` and `b`.
+pub fn distance_to_segment(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let segment = b - a;
+    let t = (point - a).dot(segment) / segment.length_squared().max(f32::EPSILON);
+    (a + segment * t.clamp(0.0, 1.0)).distance(point)
+}
+
+fn distance_between_segments(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> f32 {
+    if segments_intersect(a, b, c, d) {
+        return 0.0;
+    }
+    distance_to_segment(a, c, d)
+        .min(distance_to_segment(b, c, d))
+        .min(distance_to_segment(c, a, b))
+        .min(distance_to_segment(d, a, b))
+}
+
+fn segments_intersect(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let side = |p: Vec2, q: Vec2, r: Vec2| (q - p).perp_dot(r - p);
+    (side(a, b, c) > 0.0) != (side(a, b, d) > 0.0) && (side(c, d, a) > 0.0) != (side(c, d, b) > 0.0)
+}
+
+fn edges(polygon: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| (*a, *b))
+}
+
+// Keeps the part of the polygon on the side of the normal, the line going
+// through the origin
+fn clip(polygon: &[Vec2], normal: Vec2) -> Vec<Vec2> {
+    let mut output = Vec::new();
+    for (a, b) in edges(polygon) {
+        let (side_a, side_b) = (a.dot(normal), b.dot(normal));
+        if side_a >= 0.0 {
+            output.push(a);
+        }
+        if (side_a >= 0.0) != (side_b >= 0.0) {
+            output.push(a + (b - a) * (side_a / (side_a - side_b)));
+        }
+    }
+    output
+}
+
+fn centroid(polygon: &[Vec2]) -> Vec2 {
+    let (mut area, mut sum) = (0.0, Vec2::ZERO);
+    for (a, b) in edges(polygon) {
+        let cross = a.perp_dot(b);
+        area += cross;
+        sum += (a + b) * cross;
+    }
+    if area.abs() < f32::EPSILON {
+        return polygon.iter().copied().sum::<Vec2>() / polygon.len() as f32;
+    }
+    sum / (3.0 * area)
+}
+
+// Scales the outline so that its farthest vertex is at a distance of 1
+fn normalize(outline: Vec<Vec2>) -> Vec<Vec2> {
+    let farthest = outline
+        .iter()
+        .map(|point| point.length())
+        .fold(f32::EPSILON, f32::max);
+    outline.into_iter().map(|point| point / farthest).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::rand::SeedableRng;
+    use macroquad::math::vec2;
+
+    fn square() -> Vec<Vec2> {
+        vec![
+            vec2(1.0, 1.0),
+            vec2(-1.0, 1.0),
+            vec2(-1.0, -1.0),
+            vec2(1.0, -1.0),
+        ]
+    }
+
+    #[test]
+    fn test_random_outline() {
+        let config = ShapeConfig::default();
+        let outline = config.random_outline(&mut SmallRng::seed_from_u64(0));
+        assert!((config.min_vertices..=config.max_vertices).contains(&outline.len()));
+        assert!(outline.iter().all(|point| point.length() <= 1.0 + 1e-5));
+        assert!(contains(&outline, Vec2::ZERO));
+        // La même graine donne la même forme
+        assert_eq!(
+            outline,
+            config.random_outline(&mut SmallRng::seed_from_u64(0))
+        );
+    }
+
+    #[test]
+    fn test_collisions() {
+        let square = square();
+        assert!(contains(&square, vec2(0.5, -0.5)));
+        assert!(!contains(&square, vec2(1.5, 0.0)));
+
+        // Le cercle touche le côté sans contenir de sommet
+        assert!(circle_intersects(&square, vec2(1.4, 0.0), 0.5));
+        assert!(!circle_intersects(&square, vec2(1.6, 1.6), 0.5));
+
+        let moved = |offset: Vec2| square.iter().map(|p| *p + offset).collect::<Vec<_>>();
+        assert!(polygons_intersect(&square, &moved(vec2(1.5, 1.5))));
+        assert!(!polygons_intersect(&square, &moved(vec2(2.5, 0.0))));
+        // Un polygone dans l'autre
+        let inner: Vec<Vec2> = square.iter().map(|p| *p * 0.5).collect();
+        assert!(polygons_intersect(&square, &inner));
+    }
+
+    #[test]
+    fn test_swept_collisions() {
+        let square = square();
+        // Le cercle traverse le carré sans s'y arrêter
+        assert!(capsule_intersects(
+            &square,
+            vec2(-3.0, 0.5),
+            vec2(3.0, 0.5),
+            0.1
+        ));
+        assert!(!capsule_intersects(
+            &square,
+            vec2(-3.0, 1.5),
+            vec2(3.0, 1.5),
+            0.4
+        ));
+        // Passing just by a corner
+        assert!(capsule_intersects(
+            &square,
+            vec2(-3.0, 1.5),
+            vec2(3.0, 1.5),
+            0.6
+        ));
+        assert!(
+            (distance_to_segment(vec2(0.0, 2.0), vec2(-1.0, 0.0), vec2(1.0, 0.0)) - 2.0).abs()
+                < 1e-6
+        );
+    }
+
+    #[test]
+    fn test_fragments_cover_outline() {
+        let (center, piece) = fragment(&square(), 0.0, PI);
+        // La moitié haute du carré, recentrée
+        assert!((center - vec2(0.0, 0.5)).length() < 1e-5);
+        assert!(piece.iter().all(|point| point.length() <= 1.0 + 1e-5));
+        assert!(contains(&piece, Vec2::ZERO));
+
+        let (center, _) = fragment(&square(), PI, 2.0 * PI);
+        assert!((center - vec2(0.0, -0.5)).length() < 1e-5);
+    }
+}
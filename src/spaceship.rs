@@ -1,27 +1,130 @@
 use macroquad::prelude::*;
 use std::f32::consts::PI;
 
+use crate::weapon::Weapon;
+use crate::world::{lerp_angle, World};
+
+/// The rules of the shield, shared by every spaceship of a game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShieldConfig {
+    pub max_shield: f32,
+    /// Damage for each size of the asteroid hit.
+    pub damage_per_size: f32,
+    /// Damage for each px/s of the asteroid hit.
+    pub damage_per_speed: f32,
+    /// Seconds during which the spaceship cannot be hit again.
+    pub invulnerability_duration: f32,
+    /// Seconds without being hit before the shield regenerates.
+    pub regeneration_delay: f32,
+    /// Shield regained each second, 0 disables the regeneration.
+    pub regeneration_rate: f32,
+}
+
+impl Default for ShieldConfig {
+    fn default() -> Self {
+        Self {
+            max_shield: 100.0,
+            damage_per_size: 20.0,
+            damage_per_speed: 0.1,
+            invulnerability_duration: 1.5,
+            regeneration_delay: 3.0,
+            regeneration_rate: 5.0,
+        }
+    }
+}
+
+impl ShieldConfig {
+    /// The damage of an asteroid of the given size moving at `speed` px/s.
+    pub fn damage(&self, size: u8, speed: f32) -> f32 {
+        self.damage_per_size * size as f32 + self.damage_per_speed * speed.abs()
+    }
+}
+
+/// The rules of the hyperspace jump.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HyperspaceConfig {
+    /// Seconds between two jumps.
+    pub cooldown: f32,
+    /// Seconds after a jump during which the spaceship cannot fire.
+    pub materialization: f32,
+    /// Chance in \[0;1] that a jump destroys the spaceship.
+    pub failure_chance: f32,
+}
+
+impl Default for HyperspaceConfig {
+    fn default() -> Self {
+        Self {
+            cooldown: 3.0,
+            materialization: 0.5,
+            failure_chance: 0.05,
+        }
+    }
+}
+
+/// Where and when a destroyed spaceship comes back.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RespawnConfig {
+    /// Distance to keep from the asteroids and saucers when appearing.
+    pub safe_radius: f32,
+    /// Seconds to wait for the center to be clear before looking for the
+    /// nearest clear place.
+    pub max_wait: f32,
+}
+
+impl Default for RespawnConfig {
+    fn default() -> Self {
+        Self {
+            safe_radius: 120.0,
+            max_wait: 2.0,
+        }
+    }
+}
+
 // Définir la structure du vaisseau
 pub struct Spaceship {
     pub position: Vec2,
-    pub speed: f32,
+    // Vitesse en px/s, indépendante de l'orientation du vaisseau
+    pub velocity: Vec2,
     pub max_speed: f32,
+    pub acceleration: f32,
+    // Fraction of the velocity lost each second, 0 keeps it forever
+    pub drag: f32,
     pub rotation: f32,
     pub turn_rate: f32,
     pub size: f32,
+    pub shield: f32,
+    // Seconds left without taking hits, and since the last hit
+    pub invulnerability: f32,
+    pub time_since_hit: f32,
+    pub weapon: Weapon,
+    // Seconds before the next hyperspace jump, and left to re-materialize
+    pub hyperspace_cooldown: f32,
+    pub materialization: f32,
+    // State at the previous tick, for the interpolation of the rendering
+    pub previous_position: Vec2,
+    pub previous_rotation: f32,
 }
 
 impl Spaceship {
-    // Méthode pour créer une nouvelle instance du vaisseau
-    pub fn new() -> Self {
+    // Méthode pour créer une nouvelle instance du vaisseau au centre du monde
+    pub fn new(world: &World) -> Self {
         Spaceship {
-            // position: vec2(100.0, 100.0), //for test
-            position: vec2(screen_width() / 2.0, screen_height() / 2.0),
-            speed: 0.0,
+            position: world.center(),
+            previous_position: world.center(),
+            previous_rotation: 0.0,
+            velocity: Vec2::ZERO,
             max_speed: 500.0,
+            acceleration: 150.0,
+            drag: 0.0,
             rotation: 0.0,  // Orientation actuelle
             turn_rate: 4.0, // Vitesse de rotation
             size: 20.0,
+            shield: ShieldConfig::default().max_shield,
+            invulnerability: 0.0,
+            time_since_hit: 0.0,
+            weapon: Weapon::default(),
+            hyperspace_cooldown: 0.0,
+            materialization: 0.0,
         }
     }
 
@@ -36,11 +139,18 @@ impl Spaceship {
         )
     }
 
-    // Méthodes pour dessiner le vaisseau sous forme de triangle
-    pub fn draw(&mut self, size: f32, debug: bool) {
+    /// Keeps the current state as the state of the previous tick.
+    pub fn save_previous(&mut self) {
+        self.previous_position = self.position;
+        self.previous_rotation = self.rotation;
+    }
+
+    // Méthodes pour dessiner le vaisseau sous forme de triangle, interpolé entre les deux derniers ticks
+    pub fn draw(&mut self, size: f32, debug: bool, alpha: f32, world: &World) {
         // Calculate the height of the equilateral triangle
         let height = size * (PI / 3.0).cos();
-        let position = self.position;
+        let position = world.lerp(self.previous_position, self.position, alpha);
+        let rotation = lerp_angle(self.previous_rotation, self.rotation, alpha);
 
         // Define the three points for the equilateral triangle
         // Front point pointing in the direction of the spaceship
@@ -49,17 +159,31 @@ impl Spaceship {
         let right = Vec2::new(-size / 2.0, -height);
 
         // Rotate the points based on the spaceship's rotation
-        let rotated_front = self.rotate_point(front, self.rotation);
-        let rotated_left = self.rotate_point(left, self.rotation);
-        let rotated_right = self.rotate_point(right, self.rotation);
+        let rotated_front = self.rotate_point(front, rotation);
+        let rotated_left = self.rotate_point(left, rotation);
+        let rotated_right = self.rotate_point(right, rotation);
 
         // Draw the triangle with rotated points, centered at position
-        draw_triangle(
-            self.position + rotated_front,
-            self.position + rotated_left,
-            self.position + rotated_right,
-            YELLOW,
-        );
+        // Blinks while protected from hits, the statistics stay drawn
+        let blinking = self.is_invulnerable() && (self.invulnerability * 10.0) as u32 % 2 == 1;
+
+        // Faded while coming back from hyperspace
+        let color = if self.is_materializing() {
+            Color::new(YELLOW.r, YELLOW.g, YELLOW.b, 0.4)
+        } else {
+            YELLOW
+        };
+        // Also across the edges it overlaps
+        if !blinking {
+            for image in world.images(position, size) {
+                draw_triangle(
+                    image + rotated_front,
+                    image + rotated_left,
+                    image + rotated_right,
+                    color,
+                );
+            }
+        }
 
         // statistiques
         if debug {
@@ -76,8 +200,9 @@ impl Spaceship {
             // Attributes
             let mut texts = Vec::from([
                 format!("x: {:.2} y: {:.2}", position.x, position.y),
-                format!("Velocity:{:.2}px/s", self.speed),
+                format!("Velocity:{:.2}px/s", self.speed()),
                 format!("Rotation:{:.2}rad", self.rotation),
+                format!("Shield:{:.1}", self.shield),
             ]);
 
             let mut debug_text_sizes: Vec<u16> = Vec::new();
@@ -121,29 +246,19 @@ impl Spaceship {
     }
 
     // Méthode pour mettre à jour la position du vaisseau
-    pub fn update(&mut self, delta_time: f64) {
-        // Calculate velocity based on rotation and max speed
-        let direction = vec2(self.rotation.cos(), self.rotation.sin());
-
-        // Prevent the spaceship from going faster than the max speed
-        if self.speed > self.max_speed {
-            self.speed = self.max_speed
+    pub fn update(&mut self, delta_time: f64, world: &World) {
+        let delta_time = delta_time as f32;
+        // Slow down with the drag, without ever turning back
+        if self.drag > 0.0 {
+            self.velocity *= (1.0 - self.drag * delta_time).max(0.0);
         }
-        // Update position using the current speed and direction
-        self.position += direction * self.speed * delta_time as f32;
+        // Prevent the spaceship from going faster than the max speed
+        self.velocity = self.velocity.clamp_length_max(self.max_speed);
+        // The momentum is kept whatever the orientation of the spaceship
+        self.position += self.velocity * delta_time;
 
         // Handle screen wrapping (loop the spaceship)
-        if self.position.x < 0.0 {
-            self.position.x = screen_width();
-        } else if self.position.x > screen_width() {
-            self.position.x = 0.0;
-        }
-
-        if self.position.y < 0.0 {
-            self.position.y = screen_height();
-        } else if self.position.y > screen_height() {
-            self.position.y = 0.0;
-        }
+        self.position = world.wrap(self.position);
     }
 
     /// Moves the object by adding rotation within the bounds of [-2PI;2PI]
@@ -151,26 +266,96 @@ impl Spaceship {
         self.rotation = (self.rotation + amount) % (PI * 2.0);
     }
 
-    /// Moves the object based on its speed, with greater force on opposing direction.
+    /// Accelerates along the heading, forward or backward, with greater force
+    /// against the current velocity.
     pub fn move_spaceship(&mut self, delta_time: f64, movement_type: bool) {
         let movement_direction = if movement_type { 1.0 } else { -1.0 };
-        // The spaceship goes in the opposite direction faster
-        let acceleration_factor = if self.speed.signum() == -movement_direction {
+        let thrust = self.heading() * movement_direction;
+        // The spaceship brakes faster than it accelerates
+        let acceleration_factor = if self.velocity.dot(thrust) < 0.0 {
             3.0
         } else {
             1.0
         };
-        let base_acceleration = 150.0;
-        let acceleration =
-            base_acceleration * movement_direction * acceleration_factor * delta_time as f32;
+        self.velocity += thrust * self.acceleration * acceleration_factor * delta_time as f32;
 
-        // Accelerate if it would not go over the max speed attribute
-        if (self.speed + acceleration).abs() < self.max_speed {
-            self.speed += acceleration;
         // Cap the speed to the max speed attribute
-        } else {
-            self.speed = self.max_speed * movement_direction;
+        self.velocity = self.velocity.clamp_length_max(self.max_speed);
+    }
+
+    /// The unit vector the spaceship is pointing to.
+    pub fn heading(&self) -> Vec2 {
+        Vec2::from_angle(self.rotation)
+    }
+
+    /// The norm of the velocity, in px/s.
+    pub fn speed(&self) -> f32 {
+        self.velocity.length()
+    }
+
+    /// Returns whether the spaceship is still protected from its last hit.
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerability > 0.0
+    }
+
+    /// Lowers the shield after a hit. Returns `true` when the shield was
+    /// already down, which destroys the spaceship.
+    pub fn take_hit(&mut self, damage: f32, config: &ShieldConfig) -> bool {
+        if self.shield <= 0.0 {
+            return true;
         }
+        self.shield = (self.shield - damage).max(0.0);
+        self.invulnerability = config.invulnerability_duration;
+        self.time_since_hit = 0.0;
+        false
+    }
+
+    /// Counts down the invulnerability and regenerates the shield once the
+    /// spaceship has not been hit for a while.
+    pub fn update_shield(&mut self, delta_time: f64, config: &ShieldConfig) {
+        let delta_time = delta_time as f32;
+        self.invulnerability = (self.invulnerability - delta_time).max(0.0);
+        self.time_since_hit += delta_time;
+        if self.time_since_hit >= config.regeneration_delay {
+            self.shield =
+                (self.shield + config.regeneration_rate * delta_time).min(config.max_shield);
+        }
+    }
+
+    /// Returns whether the hyperspace is ready.
+    pub fn can_jump(&self) -> bool {
+        self.hyperspace_cooldown <= 0.0
+    }
+
+    /// Returns whether the spaceship is still coming back from hyperspace.
+    pub fn is_materializing(&self) -> bool {
+        self.materialization > 0.0
+    }
+
+    /// Moves the spaceship to `position`, at rest, and starts the cooldown
+    /// and the re-materialization.
+    pub fn jump(&mut self, position: Vec2, config: &HyperspaceConfig) {
+        self.position = position;
+        // No interpolation across the jump
+        self.previous_position = position;
+        self.velocity = Vec2::ZERO;
+        self.hyperspace_cooldown = config.cooldown;
+        self.materialization = config.materialization;
+    }
+
+    /// Counts down the cooldown and the re-materialization of the hyperspace.
+    pub fn update_hyperspace(&mut self, delta_time: f64) {
+        let delta_time = delta_time as f32;
+        self.hyperspace_cooldown = (self.hyperspace_cooldown - delta_time).max(0.0);
+        self.materialization = (self.materialization - delta_time).max(0.0);
+    }
+
+    /// The triangle of the spaceship in the playfield, its tip at the
+    /// collision radius.
+    pub fn hull(&self) -> [Vec2; 3] {
+        let radius = self.get_collision_radius();
+        [0.0, 2.0 * PI / 3.0, -2.0 * PI / 3.0]
+            .map(|angle| self.position + Vec2::from_angle(self.rotation + angle) * radius)
     }
 
     /// Returns the collision_radius
@@ -184,16 +369,21 @@ impl Spaceship {
 mod tests {
     use super::*;
 
+    // A 200x200 world puts the spaceship at (100.0, 100.0)
+    fn test_world() -> World {
+        World::new(200.0, 200.0)
+    }
+
     #[test]
     fn test_spaceship_creation() {
-        let spaceship = Spaceship::new();
+        let spaceship = Spaceship::new(&test_world());
 
         // Vérifie que la position du vaisseau au (100.0, 100.0)
         assert_eq!(spaceship.position.x, 100.0);
         assert_eq!(spaceship.position.y, 100.0);
 
         // Vérifie que la vitesse initiale du vaisseau est 0
-        assert_eq!(spaceship.speed, 0.0);
+        assert_eq!(spaceship.velocity, Vec2::ZERO);
 
         // Vérifie que la rotation initiale est 0
         assert_eq!(spaceship.rotation, 0.0);
@@ -201,7 +391,7 @@ mod tests {
 
     #[test]
     fn test_spaceship_rotation() {
-        let mut spaceship = Spaceship::new();
+        let mut spaceship = Spaceship::new(&test_world());
 
         // Effectuer une rotation de 90° (PI / 2)
         spaceship.add_rotation(PI / 2.0);
@@ -213,15 +403,15 @@ mod tests {
     //test avancer tout droit
     #[test]
     fn test_spaceship_move_forward() {
-        let mut spaceship = Spaceship::new();
+        let mut spaceship = Spaceship::new(&test_world());
 
         // Définit la vitesse du vaisseau et l'accélère
-        spaceship.speed = 100.0;
+        spaceship.velocity = vec2(100.0, 0.0);
         spaceship.move_spaceship(1.0, true); // Fait avancer le vaisseau
 
         // Vérifie que la vitesse a été modifiée
         assert!(
-            spaceship.speed > 100.0,
+            spaceship.speed() > 100.0,
             "La vitesse devrait avoir augmenté."
         );
     }
@@ -229,28 +419,137 @@ mod tests {
     //test reculer le vaisseau
     #[test]
     fn test_spaceship_move_backward() {
-        let mut spaceship = Spaceship::new();
+        let mut spaceship = Spaceship::new(&test_world());
 
         // Définit la vitesse du vaisseau et inverse le mouvement
-        spaceship.speed = 100.0;
-        spaceship.move_spaceship(1.0, false); // Fait reculer le vaisseau
+        spaceship.velocity = vec2(100.0, 0.0);
+        spaceship.move_spaceship(0.1, false); // Fait reculer le vaisseau
 
         // Vérifie que la vitesse a été modifiée
-        assert!(spaceship.speed < 100.0, "La vitesse devrait avoir diminué.");
+        assert!(
+            spaceship.speed() < 100.0,
+            "La vitesse devrait avoir diminué."
+        );
+    }
+
+    #[test]
+    fn test_spaceship_shield_hit() {
+        let config = ShieldConfig::default();
+        let mut spaceship = Spaceship::new(&test_world());
+
+        // Une grosse collision vide le bouclier sans détruire le vaisseau
+        assert!(!spaceship.take_hit(config.damage(3, 100.0), &config));
+        assert_eq!(spaceship.shield, 30.0);
+        assert!(spaceship.is_invulnerable());
+        assert!(!spaceship.take_hit(1000.0, &config));
+        assert_eq!(spaceship.shield, 0.0);
+
+        // Le vaisseau est détruit si le bouclier était déjà à 0
+        assert!(spaceship.take_hit(1.0, &config));
+    }
+
+    #[test]
+    fn test_spaceship_shield_regeneration() {
+        let config = ShieldConfig::default();
+        let mut spaceship = Spaceship::new(&test_world());
+        spaceship.take_hit(50.0, &config);
+
+        // Pas de régénération avant le délai
+        spaceship.update_shield(2.0, &config);
+        assert_eq!(spaceship.shield, 50.0);
+        assert!(!spaceship.is_invulnerable());
+
+        spaceship.update_shield(2.0, &config);
+        assert_eq!(spaceship.shield, 60.0);
+
+        // Jamais au-dessus du maximum
+        spaceship.update_shield(100.0, &config);
+        assert_eq!(spaceship.shield, config.max_shield);
+    }
+
+    #[test]
+    fn test_spaceship_hyperspace() {
+        let config = HyperspaceConfig::default();
+        let mut spaceship = Spaceship::new(&test_world());
+        spaceship.velocity = vec2(100.0, 0.0);
+        assert!(spaceship.can_jump());
+
+        spaceship.jump(vec2(10.0, 20.0), &config);
+        assert_eq!(spaceship.position, vec2(10.0, 20.0));
+        assert_eq!(spaceship.previous_position, spaceship.position);
+        assert_eq!(spaceship.velocity, Vec2::ZERO);
+        assert!(!spaceship.can_jump());
+        assert!(spaceship.is_materializing());
+
+        spaceship.update_hyperspace(config.materialization as f64);
+        assert!(!spaceship.is_materializing());
+        assert!(!spaceship.can_jump());
+        spaceship.update_hyperspace(config.cooldown as f64);
+        assert!(spaceship.can_jump());
     }
 
     #[test]
     fn test_spaceship_max_speed() {
-        let mut spaceship = Spaceship::new();
+        let mut spaceship = Spaceship::new(&test_world());
 
         // Accélère jusqu'à dépasser la vitesse maximale
-        spaceship.speed = 600.0; // Au-dessus de la vitesse maximale
+        spaceship.velocity = vec2(600.0, 0.0); // Au-dessus de la vitesse maximale
         spaceship.move_spaceship(1.0, true); // Essaye de faire avancer le vaisseau
 
         // Vérifie que la vitesse ne dépasse pas la vitesse maximale
-        assert_eq!(
-            spaceship.speed, spaceship.max_speed,
+        assert!(
+            (spaceship.speed() - spaceship.max_speed).abs() < 1e-3,
             "La vitesse ne doit pas dépasser la vitesse maximale."
         );
+
+        // Même en diagonale, c'est la norme qui est limitée
+        spaceship.velocity = vec2(400.0, 400.0);
+        spaceship.update(0.0, &test_world());
+        assert!((spaceship.speed() - spaceship.max_speed).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_spaceship_drifts_while_rotating() {
+        let mut spaceship = Spaceship::new(&test_world());
+        spaceship.velocity = vec2(50.0, 0.0);
+
+        // Tourner ne change pas la direction du mouvement
+        spaceship.add_rotation(PI / 3.0);
+        spaceship.update(1.0, &test_world());
+        assert_eq!(spaceship.velocity, vec2(50.0, 0.0));
+        assert_eq!(spaceship.position, vec2(150.0, 100.0));
+
+        // La poussée s'ajoute selon la nouvelle orientation
+        spaceship.move_spaceship(0.1, true);
+        assert!((spaceship.velocity.x - 57.5).abs() < 1e-3);
+        assert!((spaceship.velocity.y - 15.0 * (PI / 3.0).sin()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_spaceship_brakes_faster() {
+        let mut spaceship = Spaceship::new(&test_world());
+        spaceship.velocity = vec2(100.0, 0.0);
+
+        // Pousser contre le mouvement freine trois fois plus fort
+        spaceship.rotation = PI;
+        spaceship.move_spaceship(0.1, true);
+        assert!((spaceship.velocity.x - 55.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_spaceship_drag() {
+        let mut spaceship = Spaceship::new(&test_world());
+        spaceship.velocity = vec2(100.0, 0.0);
+
+        // Sans frottement, la vitesse est conservée
+        spaceship.update(1.0, &test_world());
+        assert_eq!(spaceship.velocity, vec2(100.0, 0.0));
+
+        spaceship.drag = 0.5;
+        spaceship.update(1.0, &test_world());
+        assert_eq!(spaceship.velocity, vec2(50.0, 0.0));
+        // Le frottement arrête le vaisseau sans le faire reculer
+        spaceship.update(10.0, &test_world());
+        assert_eq!(spaceship.velocity, Vec2::ZERO);
     }
 }
@@ -0,0 +1,267 @@
+use ::rand::rngs::SmallRng;
+use ::rand::Rng;
+use macroquad::prelude::*;
+use std::f32::consts::TAU;
+
+use crate::missile::{Missile, MissileConfig};
+use crate::world::World;
+
+/// The two kinds of flying saucers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UfoKind {
+    /// Slow, big and shooting anywhere.
+    Large,
+    /// Fast, small and aiming at the spaceship.
+    Small,
+}
+
+impl UfoKind {
+    pub fn radius(&self) -> f32 {
+        match self {
+            UfoKind::Large => 30.0,
+            UfoKind::Small => 15.0,
+        }
+    }
+
+    /// Horizontal speed, in px/s.
+    pub fn speed(&self) -> f32 {
+        match self {
+            UfoKind::Large => 120.0,
+            UfoKind::Small => 180.0,
+        }
+    }
+
+    /// Points for destroying the saucer.
+    pub fn score(&self) -> u128 {
+        match self {
+            UfoKind::Large => 200,
+            UfoKind::Small => 1000,
+        }
+    }
+
+    /// The size of asteroid with the same impact on the shield.
+    pub fn size(&self) -> u8 {
+        match self {
+            UfoKind::Large => 2,
+            UfoKind::Small => 1,
+        }
+    }
+}
+
+/// The rules of the flying saucers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UfoConfig {
+    /// Seconds between two saucers.
+    pub spawn_interval: f32,
+    /// Chance in \[0;1] that a saucer is a small one.
+    pub small_chance: f32,
+    /// Seconds between two shots of a saucer.
+    pub fire_interval: f32,
+    /// Seconds between two changes of the vertical direction.
+    pub zigzag_interval: f32,
+    pub missile: MissileConfig,
+}
+
+impl Default for UfoConfig {
+    fn default() -> Self {
+        Self {
+            spawn_interval: 15.0,
+            small_chance: 0.3,
+            fire_interval: 1.2,
+            zigzag_interval: 1.0,
+            missile: MissileConfig {
+                speed: 300.0,
+                lifetime: 1.5,
+                max_distance: None,
+            },
+        }
+    }
+}
+
+pub struct Ufo {
+    pub kind: UfoKind,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub active: bool,
+    // Seconds before the next change of direction and the next shot
+    pub zigzag: f32,
+    pub reload: f32,
+    // Horizontal distance covered, the saucer leaves after crossing the world
+    pub traveled: f32,
+    // Position at the previous tick, for the interpolation of the rendering
+    pub previous_position: Vec2,
+}
+
+impl Ufo {
+    /// Creates a saucer entering from the left or the right edge of the world.
+    pub fn new_random(world: &World, config: &UfoConfig, rng: &mut SmallRng) -> Self {
+        let kind = if rng.gen::<f32>() < config.small_chance {
+            UfoKind::Small
+        } else {
+            UfoKind::Large
+        };
+        let from_left = rng.gen_bool(0.5);
+        let position = vec2(
+            if from_left { 0.0 } else { world.width },
+            rng.gen_range(0.0..world.height),
+        );
+        let direction = if from_left { 1.0 } else { -1.0 };
+        Self::new(kind, position, direction, config)
+    }
+
+    /// Creates a saucer flying horizontally, to the right if `direction` is
+    /// positive.
+    pub fn new(kind: UfoKind, position: Vec2, direction: f32, config: &UfoConfig) -> Self {
+        Self {
+            kind,
+            position,
+            velocity: vec2(kind.speed() * direction.signum(), 0.0),
+            active: true,
+            zigzag: config.zigzag_interval,
+            reload: config.fire_interval,
+            traveled: 0.0,
+            previous_position: position,
+        }
+    }
+
+    /// Keeps the current position as the position of the previous tick.
+    pub fn save_previous(&mut self) {
+        self.previous_position = self.position;
+    }
+
+    /// Moves the saucer, changing its vertical direction from time to time.
+    /// It leaves once it has crossed the world.
+    pub fn update(
+        &mut self,
+        delta_time: f64,
+        world: &World,
+        config: &UfoConfig,
+        rng: &mut SmallRng,
+    ) {
+        let delta_time = delta_time as f32;
+        self.zigzag -= delta_time;
+        if self.zigzag <= 0.0 {
+            self.zigzag = config.zigzag_interval;
+            // Up, down or straight, at the same horizontal speed
+            self.velocity.y = self.kind.speed() * rng.gen_range(-1..=1) as f32;
+        }
+
+        self.position = world.wrap(self.position + self.velocity * delta_time);
+        self.reload -= delta_time;
+        self.traveled += self.velocity.x.abs() * delta_time;
+        if self.traveled >= world.width {
+            self.active = false;
+        }
+    }
+
+    /// Fires a missile when the saucer has reloaded: anywhere for a large
+    /// saucer, at `target` for a small one, the shortest way through the
+    /// edges of the playfield.
+    pub fn fire(
+        &mut self,
+        target: Vec2,
+        world: &World,
+        config: &UfoConfig,
+        rng: &mut SmallRng,
+    ) -> Option<Missile> {
+        if self.reload > 0.0 {
+            return None;
+        }
+        self.reload = config.fire_interval;
+        let rotation = match self.kind {
+            UfoKind::Large => rng.gen_range(0.0..TAU),
+            UfoKind::Small => world.delta(self.position, target).to_angle(),
+        };
+        Some(Missile::new(
+            self.position,
+            Vec2::ZERO,
+            rotation,
+            &config.missile,
+        ))
+    }
+
+    /// Draws the saucer with lines, interpolated between the last two ticks.
+    pub fn draw(&self, alpha: f32, world: &World) {
+        if !self.active {
+            return;
+        }
+        let position = world.lerp(self.previous_position, self.position, alpha);
+        let r = self.kind.radius();
+        // Hull, rim and dome of the saucer
+        let outline = [
+            vec2(-r, 0.0),
+            vec2(-r / 2.0, r / 3.0),
+            vec2(r / 2.0, r / 3.0),
+            vec2(r, 0.0),
+            vec2(r / 2.0, -r / 3.0),
+            vec2(r / 4.0, -r * 2.0 / 3.0),
+            vec2(-r / 4.0, -r * 2.0 / 3.0),
+            vec2(-r / 2.0, -r / 3.0),
+        ];
+        for position in world.images(position, r) {
+            for (index, point) in outline.iter().enumerate() {
+                let next = outline[(index + 1) % outline.len()];
+                let (a, b) = (position + *point, position + next);
+                draw_line(a.x, a.y, b.x, b.y, 2.0, LIME);
+            }
+            let (left, right) = (position - vec2(r, 0.0), position + vec2(r, 0.0));
+            draw_line(left.x, left.y, right.x, right.y, 2.0, LIME);
+            let (left, right) = (
+                position + vec2(-r / 2.0, -r / 3.0),
+                position + vec2(r / 2.0, -r / 3.0),
+            );
+            draw_line(left.x, left.y, right.x, right.y, 2.0, LIME);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::rand::SeedableRng;
+
+    #[test]
+    fn test_ufo_crosses_world_once() {
+        let world = World::new(600.0, 400.0);
+        let config = UfoConfig::default();
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut ufo = Ufo::new(UfoKind::Large, vec2(0.0, 200.0), 1.0, &config);
+
+        // La soucoupe zigzague sans jamais quitter le monde
+        for _ in 0..60 {
+            ufo.update(0.05, &world, &config, &mut rng);
+            assert!(world.contains(ufo.position));
+            assert_eq!(ufo.velocity.x, UfoKind::Large.speed());
+        }
+        assert!(ufo.active);
+        ufo.update(2.0, &world, &config, &mut rng);
+        assert!(!ufo.active);
+    }
+
+    #[test]
+    fn test_small_ufo_aims_at_target() {
+        let config = UfoConfig::default();
+        let world = World::new(800.0, 600.0);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut ufo = Ufo::new(UfoKind::Small, vec2(100.0, 100.0), -1.0, &config);
+
+        assert!(ufo
+            .fire(vec2(100.0, 300.0), &world, &config, &mut rng)
+            .is_none());
+        ufo.reload = 0.0;
+        let missile = ufo
+            .fire(vec2(100.0, 300.0), &world, &config, &mut rng)
+            .unwrap();
+        assert!(missile.velocity.x.abs() < 1e-3);
+        assert!((missile.velocity.y - config.missile.speed).abs() < 1e-3);
+        assert_eq!(ufo.reload, config.fire_interval);
+
+        // La cible est plus près en passant par le bord du haut
+        ufo.reload = 0.0;
+        let missile = ufo
+            .fire(vec2(100.0, 550.0), &world, &config, &mut rng)
+            .unwrap();
+        assert!(missile.velocity.x.abs() < 1e-3);
+        assert!((missile.velocity.y + config.missile.speed).abs() < 1e-3);
+    }
+}
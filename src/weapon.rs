@@ -0,0 +1,108 @@
+/// The gun of the spaceship: its rules and its state during a game.
+///
+/// A shot needs the cooldown of the last one to be over and fewer than
+/// `max_missiles` missiles in flight. With `overheat` enabled, every shot
+/// heats the gun up; once full, it cannot fire before it has cooled down.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weapon {
+    /// Seconds between two shots.
+    pub cooldown: f32,
+    pub max_missiles: usize,
+    pub overheat: bool,
+    /// Heat added by a shot, the gun overheats at 1.
+    pub heat_per_shot: f32,
+    /// Heat lost each second.
+    pub cooling_rate: f32,
+    // Seconds before the next shot, and current heat in [0;1]
+    pub reload: f32,
+    pub heat: f32,
+    pub overheated: bool,
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Self {
+            cooldown: 0.15,
+            max_missiles: 4,
+            overheat: true,
+            heat_per_shot: 0.25,
+            cooling_rate: 0.8,
+            reload: 0.0,
+            heat: 0.0,
+            overheated: false,
+        }
+    }
+}
+
+impl Weapon {
+    /// Returns whether a missile can be fired with `missiles` already in flight.
+    pub fn can_fire(&self, missiles: usize) -> bool {
+        self.reload <= 0.0 && missiles < self.max_missiles && !self.overheated
+    }
+
+    /// Starts the cooldown and heats the gun up after a shot.
+    pub fn fire(&mut self) {
+        self.reload = self.cooldown;
+        if self.overheat {
+            self.heat = (self.heat + self.heat_per_shot).min(1.0);
+            if self.heat >= 1.0 {
+                self.overheated = true;
+            }
+        }
+    }
+
+    /// Counts down the cooldown and cools the gun down. An overheated gun
+    /// fires again once it is completely cold.
+    pub fn update(&mut self, delta_time: f64) {
+        let delta_time = delta_time as f32;
+        self.reload = (self.reload - delta_time).max(0.0);
+        self.heat = (self.heat - self.cooling_rate * delta_time).max(0.0);
+        if self.heat <= 0.0 {
+            self.overheated = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weapon_cooldown() {
+        let mut weapon = Weapon {
+            overheat: false,
+            ..Weapon::default()
+        };
+        assert!(weapon.can_fire(0));
+        weapon.fire();
+        assert!(!weapon.can_fire(0));
+
+        weapon.update(0.1);
+        assert!(!weapon.can_fire(0));
+        weapon.update(0.1);
+        assert!(weapon.can_fire(0));
+
+        // Pas plus de missiles en vol que le maximum
+        assert!(!weapon.can_fire(weapon.max_missiles));
+    }
+
+    #[test]
+    fn test_weapon_overheat() {
+        let mut weapon = Weapon {
+            cooldown: 0.0,
+            ..Weapon::default()
+        };
+        for _ in 0..4 {
+            assert!(weapon.can_fire(0));
+            weapon.fire();
+        }
+        assert!(weapon.overheated);
+        assert!(!weapon.can_fire(0));
+
+        // Refroidi à moitié, l'arme reste bloquée
+        weapon.update(0.625);
+        assert!(!weapon.can_fire(0));
+        weapon.update(0.625);
+        assert!(weapon.can_fire(0));
+    }
+}
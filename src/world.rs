@@ -0,0 +1,192 @@
+use macroquad::prelude::*;
+use std::f32::consts::PI;
+
+/// The bounds of the playfield.
+///
+/// The models never query the window directly: the size of the playfield is
+/// carried by the [`Gamestate`](crate::general::Gamestate) and given to every
+/// update, so the simulation can run without any graphics context.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct World {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl World {
+    /// Create a playfield of the given dimensions.
+    pub fn new(width: f32, height: f32) -> Self {
+        Self { width, height }
+    }
+
+    /// Returns whether both dimensions are finite and positive, as needed
+    /// to draw random positions in the playfield.
+    pub fn is_valid(&self) -> bool {
+        [self.width, self.height]
+            .iter()
+            .all(|side| side.is_finite() && *side > 0.0)
+    }
+
+    /// Returns the center of the playfield.
+    pub fn center(&self) -> Vec2 {
+        vec2(self.width / 2.0, self.height / 2.0)
+    }
+
+    /// Returns whether the position is inside the playfield.
+    pub fn contains(&self, pos: Vec2) -> bool {
+        pos.x >= 0.0 && pos.x <= self.width && pos.y >= 0.0 && pos.y <= self.height
+    }
+
+    /// Moves a position that left the playfield to the opposite edge.
+    pub fn wrap(&self, mut pos: Vec2) -> Vec2 {
+        pos.x = Self::wrap_coord(pos.x, self.width);
+        pos.y = Self::wrap_coord(pos.y, self.height);
+        pos
+    }
+
+    /// The shortest offset from one position to another, which may go
+    /// through the edges.
+    pub fn delta(&self, from: Vec2, to: Vec2) -> Vec2 {
+        let mut delta = to - from;
+        if delta.x.abs() > self.width / 2.0 {
+            delta.x -= self.width * delta.x.signum();
+        }
+        if delta.y.abs() > self.height / 2.0 {
+            delta.y -= self.height * delta.y.signum();
+        }
+        delta
+    }
+
+    /// The distance between two positions, through the edges when shorter.
+    pub fn distance(&self, a: Vec2, b: Vec2) -> f32 {
+        self.delta(a, b).length()
+    }
+
+    /// Interpolates between two positions of an object.
+    ///
+    /// When the object went through an edge the interpolation follows the
+    /// shortest way across it instead of crossing the whole playfield.
+    pub fn lerp(&self, from: Vec2, to: Vec2, alpha: f32) -> Vec2 {
+        let pos = from + self.delta(from, to) * alpha;
+        vec2(pos.x.rem_euclid(self.width), pos.y.rem_euclid(self.height))
+    }
+
+    /// The positions where an object of the given radius is seen: its own,
+    /// and across the opposite edges for the part overlapping an edge.
+    pub fn images(&self, pos: Vec2, radius: f32) -> Vec<Vec2> {
+        let offsets = |coord: f32, max: f32| {
+            let mut offsets = vec![0.0];
+            if coord < radius {
+                offsets.push(max);
+            }
+            if coord > max - radius {
+                offsets.push(-max);
+            }
+            offsets
+        };
+        let mut images = Vec::new();
+        for y in offsets(pos.y, self.height) {
+            for x in offsets(pos.x, self.width) {
+                images.push(pos + vec2(x, y));
+            }
+        }
+        images
+    }
+
+    // Keeps the distance gone past the edge, so the motion stays continuous
+    fn wrap_coord(coord: f32, max: f32) -> f32 {
+        if max > 0.0 {
+            coord.rem_euclid(max)
+        } else {
+            coord
+        }
+    }
+}
+
+/// Interpolates between two angles in radians along the shortest rotation.
+pub fn lerp_angle(from: f32, to: f32, alpha: f32) -> f32 {
+    let delta = (to - from + PI).rem_euclid(2.0 * PI) - PI;
+    from + delta * alpha
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_world_center() {
+        let world = World::new(800.0, 600.0);
+        assert_eq!(world.center(), vec2(400.0, 300.0));
+    }
+
+    #[test]
+    fn test_world_wrap() {
+        let world = World::new(800.0, 600.0);
+
+        // Inside the playfield nothing changes
+        assert_eq!(world.wrap(vec2(10.0, 20.0)), vec2(10.0, 20.0));
+        // Outside it reappears on the opposite edge, as far as it went past
+        assert_eq!(world.wrap(vec2(-1.0, 601.0)), vec2(799.0, 1.0));
+        assert_eq!(world.wrap(vec2(801.0, -1.0)), vec2(1.0, 599.0));
+    }
+
+    #[test]
+    fn test_world_distance() {
+        let world = World::new(800.0, 600.0);
+        assert_eq!(world.distance(vec2(10.0, 10.0), vec2(40.0, 50.0)), 50.0);
+        // Through the corner
+        assert_eq!(
+            world.delta(vec2(790.0, 595.0), vec2(20.0, 35.0)),
+            vec2(30.0, 40.0)
+        );
+        assert_eq!(world.distance(vec2(790.0, 595.0), vec2(20.0, 35.0)), 50.0);
+    }
+
+    #[test]
+    fn test_world_images() {
+        let world = World::new(800.0, 600.0);
+        assert_eq!(
+            world.images(vec2(400.0, 300.0), 20.0),
+            vec![vec2(400.0, 300.0)]
+        );
+        assert_eq!(
+            world.images(vec2(790.0, 300.0), 20.0),
+            vec![vec2(790.0, 300.0), vec2(-10.0, 300.0)]
+        );
+        // Un coin donne trois fantômes
+        assert_eq!(world.images(vec2(5.0, 5.0), 20.0).len(), 4);
+    }
+
+    #[test]
+    fn test_world_lerp() {
+        let world = World::new(800.0, 600.0);
+
+        assert_eq!(
+            world.lerp(vec2(0.0, 0.0), vec2(10.0, 20.0), 0.5),
+            vec2(5.0, 10.0)
+        );
+        // Through the right edge, not back across the playfield
+        assert_eq!(
+            world.lerp(vec2(790.0, 300.0), vec2(10.0, 300.0), 0.25),
+            vec2(795.0, 300.0)
+        );
+        assert_eq!(
+            world.lerp(vec2(790.0, 300.0), vec2(10.0, 300.0), 0.75),
+            vec2(5.0, 300.0)
+        );
+    }
+
+    #[test]
+    fn test_lerp_angle() {
+        assert!((lerp_angle(0.0, 1.0, 0.5) - 0.5).abs() < 1e-6);
+        // From just under 2PI to just over 0, through 2PI
+        let angle = lerp_angle(2.0 * PI - 0.1, 0.1, 0.5);
+        assert!((angle.rem_euclid(2.0 * PI)).abs() < 1e-5 || (angle - 2.0 * PI).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_world_contains() {
+        let world = World::new(800.0, 600.0);
+        assert!(world.contains(vec2(0.0, 600.0)));
+        assert!(!world.contains(vec2(-0.1, 300.0)));
+    }
+}